@@ -1,18 +1,43 @@
-use std::sync::{Arc, Mutex};
-
-use crate::{ButtonAction, TrainID, TrainMaterials};
-use bevy::prelude::*;
-use tracks::Train;
+use crate::{
+    ButtonAction, ControlAction, StatsText, TrainID, TrainMaterials, TrainRow, PANEL_WIDTH,
+};
+use bevy::{prelude::*, ui::FocusPolicy};
+use tracks::{LockRecover, Simulation, Train};
 
 pub struct ButtonUiPlugin;
 
+/// Barra que escolhe a velocidade do trem pela posição do clique.
+#[derive(Component)]
+struct VelocitySlider(usize);
+
+/// Parte preenchida do [`VelocitySlider`], proporcional à velocidade atual.
+#[derive(Component)]
+struct VelocityFill(usize);
+
+/// Velocidade atual do trem, ao lado dos controles.
+#[derive(Component)]
+struct VelocityText(usize);
+
+/// Escala de tempo atual, entre os botões que a mudam.
+#[derive(Component)]
+struct TimeScaleText;
+
+const CONTROL_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+
+/// Escalas de tempo por que passam os botões de câmera lenta e avanço rápido.
+const TIME_SCALES: [f64; 8] = [0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0];
+
 impl Plugin for ButtonUiPlugin {
     fn build(&self, app: &mut App) {
         app.add_startup_stage(
             "spawn_button_entities",
             SystemStage::single(spawn_button_entities),
         )
-        .add_system(press_button);
+        .add_system(press_button)
+        .add_system(press_control_button)
+        .add_system(drag_slider)
+        .add_system(velocity_update)
+        .add_system(time_scale_update);
     }
 }
 
@@ -24,20 +49,13 @@ fn spawn_button_entities(
 ) {
     println!("spawn_button_entities");
 
-    let velocity_texts = vec!["Velocidade ++".to_string(), "Velocidade --".to_string()];
-
-    let train_colors = vec![
-        train_materials.green_train_material.clone(),
-        train_materials.purple_train_material.clone(),
-        train_materials.red_train_material.clone(),
-        train_materials.blue_train_material.clone(),
-    ];
+    let control_texts = ["Pausar todos".to_string(), "Reiniciar".to_string()];
 
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
                 margin: Rect::all(Val::Percent(0.5)),
-                size: Size::new(Val::Percent(25.0), Val::Percent(100.0)),
+                size: Size::new(Val::Percent(PANEL_WIDTH), Val::Percent(100.0)),
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
 
@@ -53,49 +71,235 @@ fn spawn_button_entities(
             ..Default::default()
         })
         .with_children(|root| {
-            for (color, train_id) in train_colors.iter().zip([
-                TrainID::GREEN,
-                TrainID::PURPLE,
-                TrainID::RED,
-                TrainID::BLUE,
-            ]) {
+            for (index, color) in train_materials.materials.iter().enumerate() {
                 let color_material = materials.get(color.clone()).unwrap();
 
-                for (text, button_action) in velocity_texts
-                    .iter()
-                    .zip([ButtonAction::INCREMENT, ButtonAction::DECREMENT])
-                {
-                    root.spawn_bundle(ButtonBundle {
-                        style: Style {
-                            size: Size::new(Val::Px(150.0), Val::Px(65.0)),
-                            margin: Rect::all(Val::Percent(1.0)),
-                            justify_content: JustifyContent::Center,
-                            align_items: AlignItems::Center,
+                // uma linha de botões por trem
+                root.spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(45.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    color: UiColor(Color::NONE),
+                    ..Default::default()
+                })
+                .insert(TrainRow(index))
+                .with_children(|row| {
+                    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+                    let color = color_material.color;
+                    let train = TrainID(index);
+                    spawn_button(row, "-", ButtonAction::DECREMENT, train, color, &font);
+                    spawn_slider(row, index, color);
+                    spawn_button(row, "+", ButtonAction::INCREMENT, train, color, &font);
+                    spawn_button(row, "||", ButtonAction::PAUSE, train, color, &font);
 
+                    row.spawn_bundle(TextBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(36.0), Val::Undefined),
+                            margin: Rect::all(Val::Px(2.0)),
                             ..Default::default()
                         },
-                        color: UiColor(color_material.color),
+                        text: Text::with_section(
+                            "",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                                font_size: 14.0,
+                                color: Color::BLACK,
+                            },
+                            Default::default(),
+                        ),
                         ..Default::default()
                     })
-                    .insert(train_id.clone())
-                    .insert(button_action)
-                    .with_children(|parent| {
-                        parent.spawn_bundle(TextBundle {
-                            text: Text::with_section(
-                                text,
-                                TextStyle {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 20.0,
-                                    color: Color::rgb(0.9, 0.9, 0.9),
-                                },
-                                Default::default(),
-                            ),
-                            ..Default::default()
-                        });
+                    .insert(VelocityText(index));
+                });
+            }
+
+            for (text, control_action) in control_texts
+                .iter()
+                .zip([ControlAction::PAUSE, ControlAction::RESTART])
+            {
+                root.spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                        margin: Rect::all(Val::Percent(1.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+
+                        ..Default::default()
+                    },
+                    color: UiColor(CONTROL_COLOR),
+                    ..Default::default()
+                })
+                .insert(control_action)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            text,
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 20.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
                     });
-                }
+                });
             }
+
+            // câmera lenta e avanço rápido
+            root.spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Px(45.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                color: UiColor(Color::NONE),
+                ..Default::default()
+            })
+            .with_children(|row| {
+                let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+                spawn_time_button(row, "«", ControlAction::SLOWER, &font);
+                row.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(6.0)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 16.0,
+                            color: Color::BLACK,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(TimeScaleText);
+                spawn_time_button(row, "»", ControlAction::FASTER, &font);
+            });
+
+            root.spawn_bundle(TextBundle {
+                style: Style {
+                    max_size: Size::new(Val::Px(150.0), Val::Undefined),
+                    margin: Rect::all(Val::Percent(1.0)),
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 12.0,
+                        color: Color::BLACK,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            })
+            .insert(StatsText);
+        });
+}
+
+fn spawn_button(
+    row: &mut ChildBuilder,
+    text: &str,
+    button_action: ButtonAction,
+    train: TrainID,
+    color: Color,
+    font: &Handle<Font>,
+) {
+    row.spawn_bundle(ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(30.0), Val::Px(30.0)),
+            margin: Rect::all(Val::Px(2.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: UiColor(color),
+        ..Default::default()
+    })
+    .insert(train)
+    .insert(button_action)
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section(
+                text,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+                Default::default(),
+            ),
+            ..Default::default()
         });
+    });
+}
+
+fn spawn_time_button(
+    row: &mut ChildBuilder,
+    text: &str,
+    control_action: ControlAction,
+    font: &Handle<Font>,
+) {
+    row.spawn_bundle(ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(40.0), Val::Px(30.0)),
+            margin: Rect::all(Val::Px(2.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: UiColor(CONTROL_COLOR),
+        ..Default::default()
+    })
+    .insert(control_action)
+    .with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section(
+                text,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        });
+    });
+}
+
+fn spawn_slider(row: &mut ChildBuilder, train: usize, color: Color) {
+    row.spawn_bundle(ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Undefined, Val::Px(14.0)),
+            flex_grow: 1.0,
+            margin: Rect::all(Val::Px(2.0)),
+            ..Default::default()
+        },
+        color: UiColor(Color::rgb(0.75, 0.75, 0.75)),
+        ..Default::default()
+    })
+    .insert(VelocitySlider(train))
+    .with_children(|slider| {
+        slider
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                    ..Default::default()
+                },
+                color: UiColor(color),
+                focus_policy: FocusPolicy::Pass,
+                ..Default::default()
+            })
+            .insert(VelocityFill(train));
+    });
 }
 
 fn press_button(
@@ -103,7 +307,7 @@ fn press_button(
         (&Interaction, &mut UiColor, &ButtonAction, &TrainID),
         (Changed<Interaction>, With<Button>),
     >,
-    trains: Res<Vec<Arc<Mutex<Train>>>>,
+    simulation: Res<Simulation>,
     train_materials: Res<TrainMaterials>,
     materials: ResMut<Assets<ColorMaterial>>,
 ) {
@@ -115,14 +319,8 @@ fn press_button(
                 *material = UiColor(Color::rgb(0.35, 0.75, 0.35));
             }
             Interaction::None => {
-                let color_material = match train_id {
-                    TrainID::GREEN => train_materials.green_train_material.clone(),
-                    TrainID::PURPLE => train_materials.purple_train_material.clone(),
-                    TrainID::RED => train_materials.red_train_material.clone(),
-                    TrainID::BLUE => train_materials.blue_train_material.clone(),
-                };
-
-                let color_material = materials.get(color_material.clone()).unwrap();
+                let color_material = train_materials.materials[train_id.0].clone();
+                let color_material = materials.get(color_material).unwrap();
                 *material = UiColor(color_material.color);
                 return;
             }
@@ -131,16 +329,154 @@ fn press_button(
             }
         }
 
-        let mut train = match train_id {
-            TrainID::GREEN => trains[0].lock().unwrap(),
-            TrainID::PURPLE => trains[1].lock().unwrap(),
-            TrainID::RED => trains[2].lock().unwrap(),
-            TrainID::BLUE => trains[3].lock().unwrap(),
+        let train = train_id.0;
+
+        let result = match button_action {
+            ButtonAction::INCREMENT => simulation.increment(train),
+            ButtonAction::DECREMENT => simulation.decrement(train),
+            ButtonAction::PAUSE if simulation.is_paused(train) => simulation.resume(train),
+            ButtonAction::PAUSE => simulation.pause(train),
         };
+        // por exemplo acelerar um trem que já está na velocidade máxima
+        if let Err(error) = result {
+            warn!("{}", error);
+        }
+    });
+}
+
+type ControlButtons<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut UiColor,
+        &'static ControlAction,
+    ),
+    (Changed<Interaction>, With<Button>),
+>;
 
-        match button_action {
-            ButtonAction::INCREMENT => train.increment(),
-            ButtonAction::DECREMENT => train.decrement(),
+fn press_control_button(mut query: ControlButtons, mut simulation: ResMut<Simulation>) {
+    query.for_each_mut(|(interaction, mut material, control_action)| {
+        match *interaction {
+            Interaction::Clicked => {
+                *material = UiColor(Color::rgb(0.35, 0.75, 0.35));
+            }
+            Interaction::None => {
+                *material = UiColor(CONTROL_COLOR);
+                return;
+            }
+            _ => {
+                return;
+            }
+        }
+
+        match control_action {
+            ControlAction::PAUSE => {
+                let paused = (0..simulation.trains.len()).all(|i| simulation.is_paused(i));
+                if paused {
+                    simulation.resume_all();
+                } else {
+                    simulation.pause_all();
+                }
+            }
+            ControlAction::RESTART => {
+                if let Err(error) = simulation.restart() {
+                    warn!("{}", error);
+                }
+            }
+            ControlAction::SLOWER | ControlAction::FASTER => {
+                let faster = matches!(control_action, ControlAction::FASTER);
+                let scale = next_time_scale(simulation.time_scale(), faster);
+                if let Err(error) = simulation.set_time_scale(scale) {
+                    warn!("{}", error);
+                }
+            }
+        }
+    });
+}
+
+/// A escala de [`TIME_SCALES`] logo acima (ou abaixo) de `scale`, parando nos extremos.
+fn next_time_scale(scale: f64, faster: bool) -> f64 {
+    if faster {
+        let next = TIME_SCALES.iter().find(|&&next| next > scale * 1.001);
+        next.copied().unwrap_or(TIME_SCALES[TIME_SCALES.len() - 1])
+    } else {
+        let next = TIME_SCALES.iter().rev().find(|&&next| next < scale * 0.999);
+        next.copied().unwrap_or(TIME_SCALES[0])
+    }
+}
+
+fn time_scale_update(
+    simulation: Res<Simulation>,
+    mut texts: Query<&mut Text, With<TimeScaleText>>,
+) {
+    let value = format!("{}x", simulation.time_scale());
+    texts.for_each_mut(|mut text| {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    });
+}
+
+/// Enquanto o botão do mouse está pressionado sobre a barra, a velocidade
+/// acompanha o cursor entre o mínimo e o máximo do trem.
+fn drag_slider(
+    windows: Res<Windows>,
+    simulation: Res<Simulation>,
+    query: Query<(&Interaction, &Node, &GlobalTransform, &VelocitySlider)>,
+) {
+    let Some(cursor) = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position())
+    else {
+        return;
+    };
+
+    query.for_each(|(interaction, node, transform, slider)| {
+        if *interaction != Interaction::Clicked || node.size.x <= 0.0 {
+            return;
+        }
+        let left = transform.translation.x - node.size.x / 2.0;
+        let fraction = ((cursor.x - left) / node.size.x).clamp(0.0, 1.0) as f64;
+
+        let (min, max) = {
+            let train = simulation.trains[slider.0].lock_recover();
+            (train.min_velocity, train.max_velocity)
         };
+        let velocity = ((min + fraction * (max - min)) * 10.0).round() / 10.0;
+        // a barra vai do mínimo ao máximo, mas o arredondamento pode sair deles
+        let velocity = velocity.max(min).min(max);
+        if let Err(error) = simulation.set_velocity(slider.0, velocity) {
+            warn!("{}", error);
+        }
+    });
+}
+
+fn velocity_update(
+    simulation: Res<Simulation>,
+    mut fills: Query<(&VelocityFill, &mut Style)>,
+    mut texts: Query<(&VelocityText, &mut Text)>,
+) {
+    let trains: Vec<Train> = simulation
+        .trains
+        .iter()
+        .map(|train| train.lock_recover().clone())
+        .collect();
+
+    fills.for_each_mut(|(fill, mut style)| {
+        let train = &trains[fill.0];
+        let range = train.max_velocity - train.min_velocity;
+        let fraction = if range > 0.0 {
+            (train.velocity - train.min_velocity) / range
+        } else {
+            1.0
+        };
+        style.size.width = Val::Percent(100.0 * fraction as f32);
+    });
+    texts.for_each_mut(|(text_velocity, mut text)| {
+        let value = format!("{:.1}", trains[text_velocity.0].velocity);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
     });
 }
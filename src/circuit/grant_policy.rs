@@ -0,0 +1,188 @@
+use std::{cmp::Reverse, fmt, str::FromStr};
+
+use super::LockState;
+
+/// Em que ordem um trilho disputado é entregue aos trens que o esperam.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GrantOrder {
+    /// Quem tentar primeiro depois da liberação, como num `Mutex` comum.
+    #[default]
+    Arbitrary,
+    /// Quem pediu primeiro.
+    Fifo,
+    /// Quem tem a maior prioridade; no empate, quem pediu primeiro.
+    Priority,
+    /// O próximo trem, em ordem de trem, depois do último que recebeu o trilho.
+    RoundRobin,
+}
+
+/// Política de entrega dos trilhos. Com `inheritance`, um trem que segura um
+/// trilho esperado por outro de prioridade maior herda essa prioridade até
+/// liberá-lo, o que evita a inversão de prioridade sem limite.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GrantPolicy {
+    pub order: GrantOrder,
+    pub inheritance: bool,
+}
+
+impl GrantPolicy {
+    /// Trem que deve receber `segment` entre os que o esperam e que
+    /// `eligible` aceita; `None` se ninguém ou se a ordem é arbitrária.
+    pub fn next<E>(&self, state: &LockState, segment: usize, eligible: E) -> Option<u64>
+    where
+        E: Fn(u64) -> bool,
+    {
+        let mut waiting = state.waiting[segment]
+            .iter()
+            .copied()
+            .filter(|train| eligible(*train));
+
+        match self.order {
+            GrantOrder::Arbitrary => None,
+            GrantOrder::Fifo => waiting.next(),
+            GrantOrder::Priority => {
+                waiting.min_by_key(|train| Reverse(state.priority(*train, self.inheritance)))
+            }
+            GrantOrder::RoundRobin => {
+                let last = state.last_granted[segment];
+                // quem já passou nesta volta fica para a próxima
+                waiting.min_by_key(|train| (last.is_some_and(|last| *train <= last), *train))
+            }
+        }
+    }
+
+    /// Se `train` pode receber `segment` agora pela ordem da política.
+    pub fn allows<E>(&self, state: &LockState, train: u64, segment: usize, eligible: E) -> bool
+    where
+        E: Fn(u64) -> bool,
+    {
+        self.order == GrantOrder::Arbitrary || self.next(state, segment, eligible) == Some(train)
+    }
+}
+
+impl fmt::Display for GrantOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GrantOrder::Arbitrary => "arbitrary",
+            GrantOrder::Fifo => "fifo",
+            GrantOrder::Priority => "priority",
+            GrantOrder::RoundRobin => "round-robin",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownGrantOrder(pub String);
+
+impl fmt::Display for UnknownGrantOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown grant order {}, expected arbitrary, fifo, priority or round-robin",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownGrantOrder {}
+
+impl FromStr for GrantOrder {
+    type Err = UnknownGrantOrder;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "arbitrary" => Ok(GrantOrder::Arbitrary),
+            "fifo" => Ok(GrantOrder::Fifo),
+            "priority" => Ok(GrantOrder::Priority),
+            "round-robin" => Ok(GrantOrder::RoundRobin),
+            _ => Err(UnknownGrantOrder(name.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trens 1, 2 e 3 esperando o trilho 0 nessa ordem, com prioridades 1, 5 e 5.
+    fn state() -> LockState {
+        let mut state = LockState::new(2);
+        for train in [1, 2, 3] {
+            state.enqueue(train, 0);
+        }
+        state.priorities.extend([(1, 1), (2, 5), (3, 5)]);
+        state
+    }
+
+    fn policy(order: GrantOrder) -> GrantPolicy {
+        GrantPolicy {
+            order,
+            inheritance: false,
+        }
+    }
+
+    #[test]
+    fn arbitrary_lets_anyone_try() {
+        let state = state();
+        let arbitrary = policy(GrantOrder::Arbitrary);
+        assert_eq!(arbitrary.next(&state, 0, |_| true), None);
+        assert!(arbitrary.allows(&state, 3, 0, |_| true));
+    }
+
+    #[test]
+    fn fifo_follows_the_requests() {
+        let state = state();
+        let fifo = policy(GrantOrder::Fifo);
+        assert_eq!(fifo.next(&state, 0, |_| true), Some(1));
+        assert_eq!(fifo.next(&state, 0, |train| train != 1), Some(2));
+        assert!(!fifo.allows(&state, 3, 0, |_| true));
+        assert_eq!(fifo.next(&state, 1, |_| true), None);
+    }
+
+    #[test]
+    fn priority_ties_go_to_the_first_request() {
+        let state = state();
+        assert_eq!(
+            policy(GrantOrder::Priority).next(&state, 0, |_| true),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn round_robin_starts_after_the_last_granted() {
+        let mut state = state();
+        let round_robin = policy(GrantOrder::RoundRobin);
+        assert_eq!(round_robin.next(&state, 0, |_| true), Some(1));
+        state.last_granted[0] = Some(2);
+        assert_eq!(round_robin.next(&state, 0, |_| true), Some(3));
+        state.last_granted[0] = Some(3);
+        assert_eq!(round_robin.next(&state, 0, |_| true), Some(1));
+    }
+
+    #[test]
+    fn inheritance_lifts_the_holder_of_a_wanted_track() {
+        let mut state = state();
+        // o trem 1 segura o trilho 1, que o trem 4, de prioridade 9, espera
+        state.grant(1, 1);
+        state.enqueue(4, 1);
+        state.priorities.insert(4, 9);
+
+        let mut priority = policy(GrantOrder::Priority);
+        assert_eq!(priority.next(&state, 0, |_| true), Some(2));
+        priority.inheritance = true;
+        assert_eq!(priority.next(&state, 0, |_| true), Some(1));
+    }
+
+    #[test]
+    fn orders_round_trip_through_their_names() {
+        for order in [
+            GrantOrder::Arbitrary,
+            GrantOrder::Fifo,
+            GrantOrder::Priority,
+            GrantOrder::RoundRobin,
+        ] {
+            assert_eq!(order.to_string().parse::<GrantOrder>().unwrap(), order);
+        }
+        assert!("lifo".parse::<GrantOrder>().is_err());
+    }
+}
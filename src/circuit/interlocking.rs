@@ -0,0 +1,184 @@
+use crate::{track::TICK, EventKind, EventLog, LockRecover, LockTracker, Stopped, TrainControl};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+
+use super::{GrantPolicy, LockState, SyncStrategy};
+
+/// Cabine de intertravamento: concede os trilhos aos trens segundo a
+/// [`SyncStrategy`] escolhida e, entre os que esperam o mesmo trilho, na
+/// ordem da [`GrantPolicy`].
+pub struct Interlocking {
+    state: Mutex<LockState>,
+    released: Condvar,
+    strategy: Box<dyn SyncStrategy>,
+    policy: Mutex<GrantPolicy>,
+    tracker: Arc<LockTracker>,
+    events: Arc<EventLog>,
+}
+
+impl Interlocking {
+    pub fn new(
+        segments: usize,
+        strategy: Box<dyn SyncStrategy>,
+        tracker: Arc<LockTracker>,
+        events: Arc<EventLog>,
+    ) -> Interlocking {
+        Interlocking {
+            state: Mutex::new(LockState::new(segments)),
+            released: Condvar::new(),
+            strategy,
+            policy: Mutex::new(GrantPolicy::default()),
+            tracker,
+            events,
+        }
+    }
+
+    pub fn strategy_name(&self) -> &'static str {
+        self.strategy.name()
+    }
+
+    pub fn policy(&self) -> GrantPolicy {
+        *self.policy.lock_recover()
+    }
+
+    /// Vale já para os trens que estão esperando.
+    pub fn set_policy(&self, policy: GrantPolicy) {
+        *self.policy.lock_recover() = policy;
+        self.released.notify_all();
+    }
+
+    pub fn set_priority(&self, train: u64, priority: u32) {
+        self.state.lock_recover().priorities.insert(train, priority);
+        self.released.notify_all();
+    }
+
+    /// Prioridade com que o trem disputa os trilhos agora, já com a herdada
+    /// se a política tiver herança.
+    pub fn priority(&self, train: u64) -> u32 {
+        let inheritance = self.policy().inheritance;
+        self.state.lock_recover().priority(train, inheritance)
+    }
+
+    /// Um trem pausado não recebe trilhos e a fila passa por ele.
+    pub fn set_paused(&self, train: u64, paused: bool) {
+        let mut state = self.state.lock_recover();
+        if paused {
+            state.paused.insert(train);
+        } else {
+            state.paused.remove(&train);
+        }
+        drop(state);
+
+        self.released.notify_all();
+    }
+
+    /// Quem a política escolhe para receber o trilho quando ele estiver livre;
+    /// `None` se ninguém espera ou se a ordem é arbitrária.
+    pub fn next_in_line(&self, segment: usize) -> Option<u64> {
+        let state = self.state.lock_recover();
+        self.policy().next(&state, segment, |train| {
+            state.eligible(&*self.strategy, train, segment)
+        })
+    }
+
+    /// Trem que está com o trilho agora.
+    pub fn holder(&self, segment: usize) -> Option<u64> {
+        self.state.lock_recover().holders[segment]
+    }
+
+    /// Declara a região crítica que o trem vai percorrer e devolve a ordem
+    /// em que os trilhos devem ser pedidos.
+    pub fn enter(&self, train: u64, step: &[usize]) -> Vec<usize> {
+        let mut state = self.state.lock_recover();
+        state.claims.insert(train, step.to_vec());
+        self.strategy.order(step)
+    }
+
+    /// Bloqueia até o trilho estar livre, a estratégia permitir a entrada e
+    /// chegar a vez do trem; um trem pausado não recebe o trilho.
+    pub fn acquire(
+        &self,
+        train: u64,
+        segment: usize,
+        control: &mut TrainControl,
+    ) -> Result<(), Stopped> {
+        self.request(train, segment);
+
+        let mut state = self.state.lock_recover();
+        while control.is_paused() || !self.grant(&mut state, train, segment) {
+            state = self
+                .released
+                .wait_timeout(state, TICK)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+            control.poll()?;
+        }
+        Ok(())
+    }
+
+    /// Registra o pedido do trilho sem bloquear; o trem depois tenta obtê-lo
+    /// com [`Interlocking::try_acquire`] até conseguir.
+    pub fn request(&self, train: u64, segment: usize) {
+        self.state.lock_recover().enqueue(train, segment);
+        self.events.record(train, EventKind::Requested(segment));
+        self.tracker.waiting(train, segment);
+    }
+
+    /// Entrega o trilho se ele estiver livre, a estratégia permitir e for a
+    /// vez do trem.
+    pub fn try_acquire(&self, train: u64, segment: usize) -> bool {
+        let mut state = self.state.lock_recover();
+        self.grant(&mut state, train, segment)
+    }
+
+    /// Se [`Interlocking::try_acquire`] entregaria o trilho agora, sem entregá-lo.
+    pub fn can_acquire(&self, train: u64, segment: usize) -> bool {
+        let state = self.state.lock_recover();
+        self.grantable(&state, train, segment)
+    }
+
+    fn grantable(&self, state: &LockState, train: u64, segment: usize) -> bool {
+        state.grantable(&*self.strategy, self.policy(), train, segment)
+    }
+
+    fn grant(&self, state: &mut LockState, train: u64, segment: usize) -> bool {
+        if !self.grantable(state, train, segment) {
+            return false;
+        }
+
+        state.grant(train, segment);
+        self.tracker.acquired(train, segment);
+        self.events.record(train, EventKind::Acquired(segment));
+        true
+    }
+
+    pub fn release(&self, train: u64, segment: usize) {
+        let mut state = self.state.lock_recover();
+        if !state.release(train, segment) {
+            return;
+        }
+        self.tracker.released(train, segment);
+        self.events.record(train, EventKind::Released(segment));
+        drop(state);
+
+        self.released.notify_all();
+    }
+
+    /// Libera tudo o que o trem segura ou pediu, usado quando ele é parado.
+    pub fn release_all(&self, train: u64) {
+        let mut state = self.state.lock_recover();
+        let held: Vec<usize> = state.held_by(train).collect();
+        for segment in held {
+            state.holders[segment] = None;
+            self.tracker.released(train, segment);
+            self.events.record(train, EventKind::Released(segment));
+        }
+        state.claims.remove(&train);
+        for waiting in &mut state.waiting {
+            waiting.retain(|waiting| *waiting != train);
+        }
+        self.tracker.forget(train);
+        drop(state);
+
+        self.released.notify_all();
+    }
+}
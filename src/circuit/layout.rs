@@ -0,0 +1,350 @@
+use crate::{Error, EventLog, LockTracker, Track, MAX_VELOCITY, MIN_VELOCITY};
+use serde::Deserialize;
+use std::{collections::HashMap, fmt, fs, path::Path, sync::Arc};
+
+use super::{Interlocking, LayoutCircuit, SyncKind};
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SegmentLayout {
+    pub name: String,
+    pub distance: f64,
+    #[serde(default)]
+    pub shared: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CircuitLayout {
+    pub name: String,
+    /// Cada passo é uma região crítica travada de uma só vez.
+    pub route: Vec<Vec<String>>,
+    /// Cor do trem em hexadecimal (`"#12b796"`); sem ela a interface escolhe uma.
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default = "default_min_velocity")]
+    pub min_velocity: f64,
+    #[serde(default = "default_max_velocity")]
+    pub max_velocity: f64,
+    /// Quanto maior, antes o trem recebe um trilho disputado com a ordem de
+    /// entrega por prioridade.
+    #[serde(default)]
+    pub priority: u32,
+}
+
+fn default_min_velocity() -> f64 {
+    MIN_VELOCITY
+}
+
+fn default_max_velocity() -> f64 {
+    MAX_VELOCITY
+}
+
+impl CircuitLayout {
+    /// Componentes RGB de `color`, entre 0 e 1.
+    pub fn rgb(&self) -> Option<[f32; 3]> {
+        parse_color(self.color.as_deref()?)
+    }
+}
+
+/// Grupo de trilhos limitado a `capacity` trens pela estratégia de semáforos.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegionLayout {
+    pub name: String,
+    pub segments: Vec<String>,
+    pub capacity: usize,
+}
+
+/// Topologia da malha: os trilhos e a rota que cada trem percorre.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Layout {
+    pub segments: Vec<SegmentLayout>,
+    pub circuits: Vec<CircuitLayout>,
+    #[serde(default)]
+    pub regions: Vec<RegionLayout>,
+}
+
+#[derive(Debug)]
+pub enum LayoutError {
+    Io(std::io::Error),
+    Parse(ron::Error),
+    DuplicateSegment(String),
+    InvalidDistance(String),
+    UnknownSegment { circuit: String, segment: String },
+    InvalidRegion(String),
+    EmptyRoute(String),
+    NoCircuits,
+    NotShared(String),
+    InvalidColor { circuit: String, color: String },
+    InvalidVelocityLimits(String),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Io(error) => write!(f, "could not read layout: {}", error),
+            LayoutError::Parse(error) => write!(f, "invalid layout: {}", error),
+            LayoutError::DuplicateSegment(name) => write!(f, "segment {} declared twice", name),
+            LayoutError::InvalidDistance(name) => {
+                write!(f, "segment {} needs a positive distance", name)
+            }
+            LayoutError::UnknownSegment { circuit, segment } => {
+                write!(f, "circuit {} uses unknown segment {}", circuit, segment)
+            }
+            LayoutError::InvalidRegion(region) => write!(
+                f,
+                "region {} needs known segments and a capacity of at least 1",
+                region
+            ),
+            LayoutError::EmptyRoute(circuit) => write!(f, "circuit {} has an empty route", circuit),
+            LayoutError::NoCircuits => write!(f, "layout needs at least one circuit"),
+            LayoutError::NotShared(name) => write!(
+                f,
+                "segment {} is used by more than one circuit but is not marked as shared",
+                name
+            ),
+            LayoutError::InvalidColor { circuit, color } => write!(
+                f,
+                "circuit {} has invalid color {}, expected #rrggbb",
+                circuit, color
+            ),
+            LayoutError::InvalidVelocityLimits(circuit) => write!(
+                f,
+                "circuit {} needs 0 <= min_velocity <= max_velocity",
+                circuit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+impl From<std::io::Error> for LayoutError {
+    fn from(error: std::io::Error) -> Self {
+        LayoutError::Io(error)
+    }
+}
+
+impl From<ron::Error> for LayoutError {
+    fn from(error: ron::Error) -> Self {
+        LayoutError::Parse(error)
+    }
+}
+
+impl Layout {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Layout, LayoutError> {
+        Layout::from_ron(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_ron(source: &str) -> Result<Layout, LayoutError> {
+        let layout: Layout = ron::from_str(source)?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    /// Malha gerada com `trains` trens em fila, como `ten_trains.ron`: cada
+    /// circuito divide um trilho `S` com o seguinte. Serve para simular
+    /// muitos trens sem escrever a malha à mão. Sem trens não há malha.
+    pub fn chain(trains: usize) -> Result<Layout, LayoutError> {
+        let segment = |name: String, shared| SegmentLayout {
+            name,
+            distance: 5.0,
+            shared,
+        };
+        let mut segments = Vec::new();
+        let mut circuits = Vec::new();
+        for i in 1..=trains {
+            segments.push(segment(format!("L{}.1", i), false));
+            segments.push(segment(format!("L{}.2", i), false));
+
+            let mut route = vec![vec![format!("L{}.1", i)], vec![format!("L{}.2", i)]];
+            if i < trains {
+                segments.push(segment(format!("S{}", i), true));
+                route.push(vec![format!("S{}", i)]);
+            }
+            if i > 1 {
+                route.push(vec![format!("S{}", i - 1)]);
+            }
+
+            circuits.push(CircuitLayout {
+                name: format!("train{}", i),
+                route,
+                color: None,
+                min_velocity: MIN_VELOCITY,
+                max_velocity: MAX_VELOCITY,
+                priority: 0,
+            });
+        }
+        let layout = Layout {
+            segments,
+            circuits,
+            regions: Vec::new(),
+        };
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    pub fn segment_index(&self, name: &str) -> Option<usize> {
+        self.segments
+            .iter()
+            .position(|segment| segment.name == name)
+    }
+
+    /// Índices dos trilhos de cada passo da rota do circuito `circuit`.
+    pub fn route(&self, circuit: usize) -> Vec<Vec<usize>> {
+        self.circuits[circuit]
+            .route
+            .iter()
+            .map(|step| {
+                step.iter()
+                    .filter_map(|name| self.segment_index(name))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Circuitos cuja rota passa pelo trilho `segment`.
+    pub fn users(&self, segment: usize) -> Vec<usize> {
+        (0..self.circuits.len())
+            .filter(|&circuit| self.route(circuit).iter().flatten().any(|&s| s == segment))
+            .collect()
+    }
+
+    pub fn tracks(&self) -> Result<Vec<Arc<Track>>, Error> {
+        self.segments
+            .iter()
+            .map(|segment| Track::new(segment.name.clone(), segment.distance).map(Arc::new))
+            .collect()
+    }
+
+    pub fn segment_names(&self) -> Vec<String> {
+        self.segments
+            .iter()
+            .map(|segment| segment.name.clone())
+            .collect()
+    }
+
+    pub fn lock_tracker(&self) -> LockTracker {
+        LockTracker::new(self.segment_names())
+    }
+
+    pub fn event_log(&self) -> EventLog {
+        EventLog::new(self.segment_names())
+    }
+
+    pub fn interlocking(
+        &self,
+        sync: SyncKind,
+        tracker: Arc<LockTracker>,
+        events: Arc<EventLog>,
+    ) -> Interlocking {
+        Interlocking::new(self.segments.len(), sync.build(self), tracker, events)
+    }
+
+    pub fn circuits(
+        &self,
+        tracks: &[Arc<Track>],
+        interlocking: &Arc<Interlocking>,
+        events: &Arc<EventLog>,
+    ) -> Result<Vec<LayoutCircuit>, Error> {
+        self.circuits
+            .iter()
+            .map(|circuit| {
+                LayoutCircuit::new(self, circuit, tracks, interlocking.clone(), events.clone())
+            })
+            .collect()
+    }
+
+    /// As mesmas verificações de [`Layout::from_ron`], para malhas montadas
+    /// no código.
+    pub fn validate(&self) -> Result<(), LayoutError> {
+        // a interface e o teclado contam com ao menos um trem
+        if self.circuits.is_empty() {
+            return Err(LayoutError::NoCircuits);
+        }
+
+        let mut users: HashMap<&str, Vec<&str>> = HashMap::new();
+        for segment in &self.segments {
+            if users.insert(&segment.name, Vec::new()).is_some() {
+                return Err(LayoutError::DuplicateSegment(segment.name.clone()));
+            }
+            if !(segment.distance > 0.0 && segment.distance.is_finite()) {
+                return Err(LayoutError::InvalidDistance(segment.name.clone()));
+            }
+        }
+
+        for circuit in &self.circuits {
+            if circuit.route.is_empty() || circuit.route.iter().any(Vec::is_empty) {
+                return Err(LayoutError::EmptyRoute(circuit.name.clone()));
+            }
+            let (min, max) = (circuit.min_velocity, circuit.max_velocity);
+            if !(0.0 <= min && min <= max && max.is_finite()) {
+                return Err(LayoutError::InvalidVelocityLimits(circuit.name.clone()));
+            }
+            if let Some(color) = &circuit.color {
+                if parse_color(color).is_none() {
+                    return Err(LayoutError::InvalidColor {
+                        circuit: circuit.name.clone(),
+                        color: color.clone(),
+                    });
+                }
+            }
+
+            for name in circuit.route.iter().flatten() {
+                match users.get_mut(name.as_str()) {
+                    Some(circuits) if !circuits.contains(&circuit.name.as_str()) => {
+                        circuits.push(&circuit.name)
+                    }
+                    Some(_) => {}
+                    None => {
+                        return Err(LayoutError::UnknownSegment {
+                            circuit: circuit.name.clone(),
+                            segment: name.clone(),
+                        })
+                    }
+                }
+            }
+        }
+
+        for region in &self.regions {
+            let unknown = region
+                .segments
+                .iter()
+                .any(|name| self.segment_index(name).is_none());
+            if unknown || region.capacity == 0 {
+                return Err(LayoutError::InvalidRegion(region.name.clone()));
+            }
+        }
+
+        for segment in &self.segments {
+            if users[segment.name.as_str()].len() > 1 && !segment.shared {
+                return Err(LayoutError::NotShared(segment.name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_color(color: &str) -> Option<[f32; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| {
+        let value = u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()?;
+        Some(value as f32 / 255.0)
+    };
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chain_needs_a_train() {
+        assert!(matches!(Layout::chain(0), Err(LayoutError::NoCircuits)));
+        let layout = Layout::chain(3).unwrap();
+        assert_eq!(layout.circuits.len(), 3);
+        assert!(layout.validate().is_ok());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{
+    track::TrainPosition, Error, EventKind, EventLog, LockRecover, Stopped, Track, Train,
+    TrainControl,
+};
+use std::sync::{Arc, Mutex};
+
+use super::{Circuit, CircuitLayout, Interlocking, Layout, LayoutError};
+
+struct Segment {
+    index: usize,
+    track: Arc<Track>,
+}
+
+pub struct LayoutCircuit {
+    name: String,
+    route: Vec<Vec<Segment>>,
+    interlocking: Arc<Interlocking>,
+    events: Arc<EventLog>,
+}
+
+impl LayoutCircuit {
+    /// Falha se a rota cita um trilho que não está na malha ou em `tracks`.
+    pub fn new(
+        layout: &Layout,
+        circuit: &CircuitLayout,
+        tracks: &[Arc<Track>],
+        interlocking: Arc<Interlocking>,
+        events: Arc<EventLog>,
+    ) -> Result<LayoutCircuit, Error> {
+        if circuit.route.is_empty() || circuit.route.iter().any(Vec::is_empty) {
+            return Err(LayoutError::EmptyRoute(circuit.name.clone()).into());
+        }
+        let route = circuit
+            .route
+            .iter()
+            .map(|step| {
+                step.iter()
+                    .map(|name| {
+                        let unknown = || Error::UnknownSegment {
+                            circuit: circuit.name.clone(),
+                            segment: name.clone(),
+                        };
+                        let index = layout.segment_index(name).ok_or_else(unknown)?;
+                        let track = tracks.get(index).ok_or_else(unknown)?.clone();
+                        Ok(Segment { index, track })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(LayoutCircuit {
+            name: circuit.name.clone(),
+            route,
+            interlocking,
+            events,
+        })
+    }
+
+    /// Faz a rota começar pelo passo `step`.
+    pub fn start_at(&mut self, step: usize) -> Result<(), Error> {
+        if step >= self.route.len() {
+            return Err(Error::UnknownStep {
+                circuit: self.name.clone(),
+                step,
+            });
+        }
+        self.route.rotate_left(step);
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run_route(
+        &self,
+        id: u64,
+        position: &Mutex<TrainPosition>,
+        train: &Mutex<Train>,
+        control: &mut TrainControl,
+    ) -> Result<(), Stopped> {
+        for step in &self.route {
+            // trava toda a região crítica antes de entrar no primeiro trilho
+            let indices: Vec<usize> = step.iter().map(|segment| segment.index).collect();
+            for index in self.interlocking.enter(id, &indices) {
+                self.interlocking.acquire(id, index, control)?;
+            }
+
+            for segment in step {
+                *position.lock_recover() = TrainPosition::new(segment.index);
+                self.events.record(id, EventKind::Entered(segment.index));
+                segment
+                    .track
+                    .run(train, position, self.events.clock(), control)?;
+
+                self.interlocking.release(id, segment.index); // libera o trilho
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Circuit for LayoutCircuit {
+    fn run(
+        &self,
+        position: Arc<Mutex<TrainPosition>>,
+        train: &Mutex<Train>,
+        control: &mut TrainControl,
+    ) -> Result<(), Stopped> {
+        let id = train.lock_recover().id;
+        let result = self.run_route(id, &position, train, control);
+        if result.is_err() {
+            // um trem parado não pode ficar segurando trilhos
+            self.interlocking.release_all(id);
+        }
+        result
+    }
+
+    fn initial_track(&self) -> usize {
+        self.route[0][0].index
+    }
+}
@@ -1,20 +1,30 @@
-use crate::{track::TrackState, Train};
+use crate::{track::TrainPosition, Stopped, Train, TrainControl};
 use std::sync::{Arc, Mutex};
 
-mod blue_circuit;
-mod green_circuit;
-mod purple_circuit;
-mod red_circuit;
+mod grant_policy;
+mod interlocking;
+mod layout;
+mod layout_circuit;
+mod sync_strategy;
 
-pub use blue_circuit::BlueCircuit;
-pub use green_circuit::GreenCircuit;
-pub use purple_circuit::PurpleCircuit;
-pub use red_circuit::RedCircuit;
+pub use grant_policy::{GrantOrder, GrantPolicy, UnknownGrantOrder};
+pub use interlocking::Interlocking;
+pub use layout::{CircuitLayout, Layout, LayoutError, RegionLayout, SegmentLayout};
+pub use layout_circuit::LayoutCircuit;
+pub use sync_strategy::{
+    BankerStrategy, LockState, MutexStrategy, OrderedStrategy, SemaphoreStrategy, SyncKind,
+    SyncStrategy, UnknownSyncKind,
+};
 
 pub trait Circuit {
-    fn run(&self, ui_state: Arc<Mutex<TrackState>>, train: &Train);
+    /// Percorre uma volta do circuito; devolve [`Stopped`] se o trem for parado.
+    fn run(
+        &self,
+        position: Arc<Mutex<TrainPosition>>,
+        train: &Mutex<Train>,
+        control: &mut TrainControl,
+    ) -> Result<(), Stopped>;
 
-    fn initial_track_state(&self) -> TrackState;
+    /// Índice do trilho em que o trem começa.
+    fn initial_track(&self) -> usize;
 }
-
-
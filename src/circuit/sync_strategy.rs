@@ -0,0 +1,357 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use super::{GrantPolicy, Layout};
+
+/// Estado compartilhado das travas, protegido pelo [`super::Interlocking`].
+pub struct LockState {
+    /// Trem que ocupa cada trilho.
+    pub holders: Vec<Option<u64>>,
+    /// Trilhos da região crítica atual de cada trem que ele ainda não obteve.
+    pub claims: HashMap<u64, Vec<usize>>,
+    /// Trens esperando cada trilho, na ordem dos pedidos.
+    pub waiting: Vec<Vec<u64>>,
+    /// Último trem que recebeu cada trilho.
+    pub last_granted: Vec<Option<u64>>,
+    /// Prioridade de cada trem; quem não aparece tem prioridade 0.
+    pub priorities: HashMap<u64, u32>,
+    /// Trens pausados, que não recebem trilhos nem seguram a fila.
+    pub paused: HashSet<u64>,
+}
+
+impl LockState {
+    pub fn new(segments: usize) -> LockState {
+        LockState {
+            holders: vec![None; segments],
+            claims: HashMap::new(),
+            waiting: vec![Vec::new(); segments],
+            last_granted: vec![None; segments],
+            priorities: HashMap::new(),
+            paused: HashSet::new(),
+        }
+    }
+
+    pub fn held_by(&self, train: u64) -> impl Iterator<Item = usize> + '_ {
+        self.holders
+            .iter()
+            .enumerate()
+            .filter(move |(_, holder)| **holder == Some(train))
+            .map(|(segment, _)| segment)
+    }
+
+    /// Põe o trem no fim da fila do trilho.
+    pub fn enqueue(&mut self, train: u64, segment: usize) {
+        if !self.waiting[segment].contains(&train) {
+            self.waiting[segment].push(train);
+        }
+    }
+
+    /// Entrega o trilho ao trem, tirando-o da fila e do que falta da região.
+    pub fn grant(&mut self, train: u64, segment: usize) {
+        self.holders[segment] = Some(train);
+        self.waiting[segment].retain(|waiting| *waiting != train);
+        self.last_granted[segment] = Some(train);
+        if let Some(claim) = self.claims.get_mut(&train) {
+            claim.retain(|claimed| *claimed != segment);
+        }
+    }
+
+    /// Se o trem pode receber o trilho agora: ele está livre, a estratégia
+    /// deixa e, entre os que o esperam, é a vez do trem pela política.
+    pub fn grantable(
+        &self,
+        strategy: &dyn SyncStrategy,
+        policy: GrantPolicy,
+        train: u64,
+        segment: usize,
+    ) -> bool {
+        self.holders[segment].is_none()
+            && strategy.may_acquire(self, train, segment)
+            && policy.allows(self, train, segment, |waiting| {
+                self.eligible(strategy, waiting, segment)
+            })
+    }
+
+    /// Se o trem disputa o trilho: não está pausado e a estratégia deixa.
+    pub fn eligible(&self, strategy: &dyn SyncStrategy, train: u64, segment: usize) -> bool {
+        !self.paused.contains(&train) && strategy.may_acquire(self, train, segment)
+    }
+
+    /// Tira o trilho do trem, se é ele quem o segura; a região sai de
+    /// `claims` quando o trem já obteve e liberou todos os trilhos dela.
+    pub fn release(&mut self, train: u64, segment: usize) -> bool {
+        if self.holders[segment] != Some(train) {
+            return false;
+        }
+
+        self.holders[segment] = None;
+        let done = self.claims.get(&train).is_none_or(Vec::is_empty);
+        if done && self.held_by(train).next().is_none() {
+            self.claims.remove(&train);
+        }
+        true
+    }
+
+    /// Prioridade do trem. Com `inheritance`, a maior entre a dele e a dos
+    /// trens que esperam, direta ou indiretamente, um trilho que ele segura.
+    pub fn priority(&self, train: u64, inheritance: bool) -> u32 {
+        let base = |train: u64| self.priorities.get(&train).copied().unwrap_or(0);
+        let mut priority = base(train);
+        if !inheritance {
+            return priority;
+        }
+
+        let mut visited = vec![train];
+        let mut pending = vec![train];
+        while let Some(holder) = pending.pop() {
+            for segment in self.held_by(holder) {
+                for waiter in &self.waiting[segment] {
+                    if !visited.contains(waiter) {
+                        visited.push(*waiter);
+                        pending.push(*waiter);
+                        priority = priority.max(base(*waiter));
+                    }
+                }
+            }
+        }
+        priority
+    }
+}
+
+/// Política de exclusão mútua das regiões críticas.
+///
+/// A exclusão de cada trilho é garantida pelo [`super::Interlocking`]; a
+/// estratégia só decide a ordem dos pedidos e se um trilho livre pode ser
+/// entregue agora.
+pub trait SyncStrategy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn order(&self, step: &[usize]) -> Vec<usize> {
+        step.to_vec()
+    }
+
+    fn may_acquire(&self, state: &LockState, train: u64, segment: usize) -> bool;
+}
+
+/// Um mutex por trilho: a região é travada trilho a trilho, na ordem da rota.
+pub struct MutexStrategy;
+
+impl SyncStrategy for MutexStrategy {
+    fn name(&self) -> &'static str {
+        "mutex"
+    }
+
+    fn may_acquire(&self, _state: &LockState, _train: u64, _segment: usize) -> bool {
+        true
+    }
+}
+
+/// Trava os trilhos sempre na ordem global dos índices, o que impede ciclos.
+pub struct OrderedStrategy;
+
+impl SyncStrategy for OrderedStrategy {
+    fn name(&self) -> &'static str {
+        "ordered"
+    }
+
+    fn order(&self, step: &[usize]) -> Vec<usize> {
+        let mut order = step.to_vec();
+        order.sort_unstable();
+        order
+    }
+
+    fn may_acquire(&self, _state: &LockState, _train: u64, _segment: usize) -> bool {
+        true
+    }
+}
+
+/// Semáforo contador por região: no máximo `capacity` trens ocupam trilhos
+/// da região ao mesmo tempo.
+pub struct SemaphoreStrategy {
+    regions: Vec<(Vec<usize>, usize)>,
+}
+
+impl SemaphoreStrategy {
+    pub fn new(layout: &Layout) -> SemaphoreStrategy {
+        let regions = layout
+            .regions
+            .iter()
+            .map(|region| {
+                let segments = region
+                    .segments
+                    .iter()
+                    .filter_map(|name| layout.segment_index(name))
+                    .collect();
+                (segments, region.capacity)
+            })
+            .collect();
+
+        SemaphoreStrategy { regions }
+    }
+}
+
+impl SyncStrategy for SemaphoreStrategy {
+    fn name(&self) -> &'static str {
+        "semaphore"
+    }
+
+    fn may_acquire(&self, state: &LockState, train: u64, segment: usize) -> bool {
+        self.regions
+            .iter()
+            .filter(|(segments, _)| segments.contains(&segment))
+            .all(|(segments, capacity)| {
+                let mut trains: Vec<u64> = segments
+                    .iter()
+                    .filter_map(|segment| state.holders[*segment])
+                    .collect();
+                trains.sort_unstable();
+                trains.dedup();
+
+                // quem já está na região já tem a sua permissão
+                trains.contains(&train) || trains.len() < *capacity
+            })
+    }
+}
+
+/// Algoritmo do banqueiro: a região crítica de cada trem é a sua demanda
+/// máxima e um trilho só é entregue se o estado resultante for seguro.
+pub struct BankerStrategy;
+
+impl BankerStrategy {
+    fn is_safe(holders: &[Option<u64>], claims: &HashMap<u64, Vec<usize>>) -> bool {
+        let mut available: Vec<bool> = holders.iter().map(Option::is_none).collect();
+        let mut pending: Vec<u64> = claims.keys().cloned().collect();
+
+        loop {
+            let finished = pending
+                .iter()
+                .position(|train| claims[train].iter().all(|segment| available[*segment]));
+
+            match finished {
+                Some(index) => {
+                    let train = pending.swap_remove(index);
+                    for (segment, holder) in holders.iter().enumerate() {
+                        if *holder == Some(train) {
+                            available[segment] = true;
+                        }
+                    }
+                }
+                None => return pending.is_empty(),
+            }
+        }
+    }
+}
+
+impl SyncStrategy for BankerStrategy {
+    fn name(&self) -> &'static str {
+        "banker"
+    }
+
+    fn may_acquire(&self, state: &LockState, train: u64, segment: usize) -> bool {
+        let mut holders = state.holders.clone();
+        holders[segment] = Some(train);
+
+        let mut claims = state.claims.clone();
+        if let Some(claim) = claims.get_mut(&train) {
+            claim.retain(|claimed| *claimed != segment);
+        }
+
+        BankerStrategy::is_safe(&holders, &claims)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncKind {
+    #[default]
+    Mutex,
+    Semaphore,
+    Banker,
+    Ordered,
+}
+
+impl SyncKind {
+    pub fn build(self, layout: &Layout) -> Box<dyn SyncStrategy> {
+        match self {
+            SyncKind::Mutex => Box::new(MutexStrategy),
+            SyncKind::Semaphore => Box::new(SemaphoreStrategy::new(layout)),
+            SyncKind::Banker => Box::new(BankerStrategy),
+            SyncKind::Ordered => Box::new(OrderedStrategy),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownSyncKind(pub String);
+
+impl fmt::Display for UnknownSyncKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown sync strategy {}, expected mutex, semaphore, banker or ordered",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownSyncKind {}
+
+impl FromStr for SyncKind {
+    type Err = UnknownSyncKind;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "mutex" => Ok(SyncKind::Mutex),
+            "semaphore" => Ok(SyncKind::Semaphore),
+            "banker" => Ok(SyncKind::Banker),
+            "ordered" => Ok(SyncKind::Ordered),
+            _ => Err(UnknownSyncKind(name.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(entries: &[(u64, &[usize])]) -> HashMap<u64, Vec<usize>> {
+        entries
+            .iter()
+            .map(|(train, segments)| (*train, segments.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn banker_accepts_trains_that_can_finish_one_after_another() {
+        // o trem 2 termina com o trilho livre e libera o 1 para o trem 1
+        let holders = [Some(1), Some(2), None];
+        assert!(BankerStrategy::is_safe(
+            &holders,
+            &claims(&[(1, &[1]), (2, &[2])])
+        ));
+        assert!(BankerStrategy::is_safe(&holders, &HashMap::new()));
+    }
+
+    #[test]
+    fn banker_rejects_a_circular_wait() {
+        let holders = [Some(1), Some(2)];
+        assert!(!BankerStrategy::is_safe(
+            &holders,
+            &claims(&[(1, &[1]), (2, &[0])])
+        ));
+    }
+
+    #[test]
+    fn banker_refuses_the_grant_that_would_deadlock() {
+        let mut state = LockState::new(2);
+        state.claims.insert(1, vec![0, 1]);
+        state.claims.insert(2, vec![1, 0]);
+        state.grant(1, 0);
+
+        // com o trilho 1 o trem 2 esperaria o 0, e o trem 1 esperaria o 1
+        assert!(!BankerStrategy.may_acquire(&state, 2, 1));
+        assert!(BankerStrategy.may_acquire(&state, 1, 1));
+    }
+}
@@ -0,0 +1,416 @@
+use std::{fmt, time::Duration};
+use tracks::circuit::{GrantOrder, SyncKind, UnknownGrantOrder, UnknownSyncKind};
+
+pub const HELP: &str = "\
+Visualização da dinâmica dos trens
+
+uso: tracks [opções]
+
+malha e cenário:
+  --layout ARQUIVO      malha a simular (padrão: assets/layouts/four_trains.ron)
+  --scenario ARQUIVO    cenário inicial; sem --layout vale a malha do cenário
+  --sync NOME           estratégia: mutex, ordered, semaphore ou banker
+                        (padrão: variável SYNC_STRATEGY ou mutex)
+  --grant ORDEM         quem recebe um trilho disputado: arbitrary, fifo,
+                        priority ou round-robin (padrão: arbitrary)
+  --inherit             herança de prioridade: quem segura um trilho herda a
+                        prioridade de quem o espera (só com --grant priority)
+  --speeds V1,V2,...    velocidade inicial de cada trem, na ordem da malha e
+                        dentro dos limites dele
+  --time-scale X        escala do tempo simulado, de 0.1 a 20 (padrão: 1)
+  --chain N             malha gerada com N trens em fila, no lugar de --layout
+
+janela:
+  --width PIXELS        largura da janela (padrão: 600)
+  --height PIXELS       altura da janela (padrão: 800)
+  --title TEXTO         título da janela
+  --ecs                 trens e travas em sistemas do Bevy, com o relógio do
+                        Bevy, em vez de uma thread por trem; o passo a passo
+                        (tecla M) só existe neste modo
+  --step                começa no passo a passo (implica --ecs): cada N, ou
+                        o botão do painel, entrega um trilho ou libera um
+
+sem janela:
+  --headless            roda sem janela, imprimindo cada troca de trilho
+  --duration SEGUNDOS   tempo simulado (padrão: 60)
+  --virtual             relógio virtual em vez de threads
+  --workers N           N threads avançam todos os trens, em vez de uma
+                        thread por trem
+  --seed N              semente do desempate no relógio virtual (padrão: 0)
+  --trace ARQUIVO       grava os eventos no fim, em CSV se ARQUIVO terminar em
+                        .csv e em JSON Lines nos outros casos
+  --stats               imprime as estatísticas no fim
+
+  -h, --help            mostra esta ajuda
+";
+
+const DEFAULT_WIDTH: f32 = 600.0;
+const DEFAULT_HEIGHT: f32 = 800.0;
+const DEFAULT_DURATION: f64 = 60.0;
+
+/// Opções da linha de comando já conferidas.
+pub struct Options {
+    pub help: bool,
+    pub layout: Option<String>,
+    pub scenario: Option<String>,
+    pub sync: Option<SyncKind>,
+    pub grant: GrantOrder,
+    pub inherit: bool,
+    pub speeds: Option<Vec<f64>>,
+    pub chain: Option<usize>,
+    pub time_scale: Option<f64>,
+    pub width: f32,
+    pub height: f32,
+    pub title: Option<String>,
+    pub ecs: bool,
+    pub step: bool,
+    pub headless: bool,
+    pub duration: Duration,
+    pub virtual_clock: bool,
+    pub workers: Option<usize>,
+    pub seed: u64,
+    pub trace: Option<String>,
+    pub stats: bool,
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    UnknownOption(String),
+    MissingValue(&'static str),
+    InvalidValue { option: &'static str, value: String },
+    UnknownSync(UnknownSyncKind),
+    UnknownGrant(UnknownGrantOrder),
+    HeadlessOnly(&'static str),
+    VirtualOnly(&'static str),
+    WindowOnly(&'static str),
+    Conflict(&'static str, &'static str),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::UnknownOption(option) => write!(f, "unknown option {}", option),
+            CliError::MissingValue(option) => write!(f, "{} expects a value", option),
+            CliError::InvalidValue { option, value } => {
+                write!(f, "invalid value {:?} for {}", value, option)
+            }
+            CliError::UnknownSync(error) => write!(f, "{}", error),
+            CliError::UnknownGrant(error) => write!(f, "{}", error),
+            CliError::HeadlessOnly(option) => write!(f, "{} only works with --headless", option),
+            CliError::VirtualOnly(option) => write!(f, "{} only works with --virtual", option),
+            CliError::WindowOnly(option) => write!(f, "{} does not work with --headless", option),
+            CliError::Conflict(option, other) => {
+                write!(f, "{} cannot be used with {}", option, other)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+fn parse_number<T: std::str::FromStr>(option: &'static str, value: &str) -> Result<T, CliError> {
+    value.parse().map_err(|_| CliError::InvalidValue {
+        option,
+        value: value.to_string(),
+    })
+}
+
+/// Número finito e maior que zero (ou maior ou igual, com `zero`).
+fn parse_positive(option: &'static str, value: &str, zero: bool) -> Result<f64, CliError> {
+    let number: f64 = parse_number(option, value)?;
+    if !number.is_finite() || number < 0.0 || (number == 0.0 && !zero) {
+        return Err(CliError::InvalidValue {
+            option,
+            value: value.to_string(),
+        });
+    }
+    Ok(number)
+}
+
+/// Inteiro maior que zero.
+fn parse_count(option: &'static str, value: &str) -> Result<usize, CliError> {
+    match parse_number(option, value)? {
+        0 => Err(CliError::InvalidValue {
+            option,
+            value: value.to_string(),
+        }),
+        count => Ok(count),
+    }
+}
+
+impl Options {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, CliError> {
+        let mut options = Options {
+            help: false,
+            layout: None,
+            scenario: None,
+            sync: None,
+            grant: GrantOrder::default(),
+            inherit: false,
+            speeds: None,
+            chain: None,
+            time_scale: None,
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            title: None,
+            ecs: false,
+            step: false,
+            headless: false,
+            duration: Duration::from_secs_f64(DEFAULT_DURATION),
+            virtual_clock: false,
+            workers: None,
+            seed: 0,
+            trace: None,
+            stats: false,
+        };
+        // opções só com sentido em um dos modos, conferidas no fim
+        let mut headless_only = None;
+        let mut window_only = None;
+        let mut seed_given = false;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let option: &'static str = match arg.as_str() {
+                "-h" | "--help" => {
+                    options.help = true;
+                    continue;
+                }
+                "--headless" => {
+                    options.headless = true;
+                    continue;
+                }
+                "--virtual" => {
+                    options.virtual_clock = true;
+                    headless_only = Some("--virtual");
+                    continue;
+                }
+                "--ecs" => {
+                    options.ecs = true;
+                    window_only = Some("--ecs");
+                    continue;
+                }
+                "--step" => {
+                    options.ecs = true;
+                    options.step = true;
+                    window_only = Some("--step");
+                    continue;
+                }
+                "--inherit" => {
+                    options.inherit = true;
+                    continue;
+                }
+                "--stats" => {
+                    options.stats = true;
+                    headless_only = Some("--stats");
+                    continue;
+                }
+                "--layout" => "--layout",
+                "--scenario" => "--scenario",
+                "--sync" => "--sync",
+                "--grant" => "--grant",
+                "--speeds" => "--speeds",
+                "--chain" => "--chain",
+                "--time-scale" => "--time-scale",
+                "--width" => "--width",
+                "--height" => "--height",
+                "--title" => "--title",
+                "--duration" => "--duration",
+                "--workers" => "--workers",
+                "--seed" => "--seed",
+                "--trace" => "--trace",
+                _ => return Err(CliError::UnknownOption(arg)),
+            };
+            let value = args.next().ok_or(CliError::MissingValue(option))?;
+
+            match option {
+                "--layout" => options.layout = Some(value),
+                "--scenario" => options.scenario = Some(value),
+                "--sync" => options.sync = Some(value.parse().map_err(CliError::UnknownSync)?),
+                "--grant" => options.grant = value.parse().map_err(CliError::UnknownGrant)?,
+                "--speeds" => {
+                    let speeds = value
+                        .split(',')
+                        .map(|speed| parse_positive(option, speed.trim(), true))
+                        .collect::<Result<_, _>>()?;
+                    options.speeds = Some(speeds);
+                }
+                "--chain" => options.chain = Some(parse_count(option, &value)?),
+                "--time-scale" => options.time_scale = Some(parse_positive(option, &value, false)?),
+                "--width" => {
+                    options.width = parse_positive(option, &value, false)? as f32;
+                    window_only = Some(option);
+                }
+                "--height" => {
+                    options.height = parse_positive(option, &value, false)? as f32;
+                    window_only = Some(option);
+                }
+                "--title" => {
+                    options.title = Some(value);
+                    window_only = Some(option);
+                }
+                "--duration" => {
+                    let seconds = parse_positive(option, &value, false)?;
+                    options.duration = Duration::from_secs_f64(seconds);
+                    headless_only = Some(option);
+                }
+                "--workers" => {
+                    options.workers = Some(parse_count(option, &value)?);
+                    headless_only = Some(option);
+                }
+                "--seed" => {
+                    options.seed = parse_number(option, &value)?;
+                    headless_only = Some(option);
+                    seed_given = true;
+                }
+                "--trace" => {
+                    options.trace = Some(value);
+                    headless_only = Some(option);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        if options.help {
+            return Ok(options);
+        }
+        match (options.headless, headless_only, window_only) {
+            (false, Some(option), _) => return Err(CliError::HeadlessOnly(option)),
+            (true, _, Some(option)) => return Err(CliError::WindowOnly(option)),
+            _ => {}
+        }
+        if seed_given && !options.virtual_clock {
+            return Err(CliError::VirtualOnly("--seed"));
+        }
+        // só a ordem por prioridade olha a prioridade herdada
+        if options.inherit && options.grant != GrantOrder::Priority {
+            return Err(CliError::Conflict(
+                "--inherit",
+                "a --grant other than priority",
+            ));
+        }
+        if options.chain.is_some() && options.layout.is_some() {
+            return Err(CliError::Conflict("--chain", "--layout"));
+        }
+        if options.workers.is_some() && options.virtual_clock {
+            return Err(CliError::Conflict("--workers", "--virtual"));
+        }
+        // no relógio virtual o tempo já não depende do relógio de parede
+        if options.time_scale.is_some() && options.virtual_clock {
+            return Err(CliError::Conflict("--time-scale", "--virtual"));
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, CliError> {
+        Options::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        let options = parse("").unwrap();
+        assert!(!options.headless && !options.ecs && !options.help);
+        assert_eq!(options.speeds, None);
+        assert_eq!(options.grant, GrantOrder::Arbitrary);
+        assert_eq!(
+            (options.width, options.height),
+            (DEFAULT_WIDTH, DEFAULT_HEIGHT)
+        );
+        assert_eq!(options.duration, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn reads_headless_values() {
+        let options = parse(
+            "--headless --virtual --seed 4 --duration 2.5 --speeds 1,2.5 --grant priority --inherit --chain 3",
+        )
+        .unwrap();
+        assert!(options.virtual_clock && options.inherit);
+        assert_eq!(options.seed, 4);
+        assert_eq!(options.duration, Duration::from_millis(2500));
+        assert_eq!(options.speeds, Some(vec![1.0, 2.5]));
+        assert_eq!(options.grant, GrantOrder::Priority);
+        assert_eq!(options.chain, Some(3));
+    }
+
+    #[test]
+    fn step_implies_ecs() {
+        let options = parse("--step").unwrap();
+        assert!(options.step && options.ecs);
+    }
+
+    #[test]
+    fn help_skips_the_mode_checks() {
+        assert!(parse("--stats --help").unwrap().help);
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert!(matches!(parse("--fast"), Err(CliError::UnknownOption(_))));
+        assert!(matches!(
+            parse("--layout"),
+            Err(CliError::MissingValue("--layout"))
+        ));
+        assert!(matches!(
+            parse("--chain 0"),
+            Err(CliError::InvalidValue {
+                option: "--chain",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("--speeds 1,-2"),
+            Err(CliError::InvalidValue {
+                option: "--speeds",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("--time-scale 0"),
+            Err(CliError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse("--sync spin"),
+            Err(CliError::UnknownSync(_))
+        ));
+        assert!(matches!(
+            parse("--grant lifo"),
+            Err(CliError::UnknownGrant(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_options_of_the_other_mode() {
+        assert!(matches!(
+            parse("--stats"),
+            Err(CliError::HeadlessOnly("--stats"))
+        ));
+        assert!(matches!(
+            parse("--headless --ecs"),
+            Err(CliError::WindowOnly("--ecs"))
+        ));
+        assert!(matches!(
+            parse("--headless --seed 1"),
+            Err(CliError::VirtualOnly("--seed"))
+        ));
+    }
+
+    #[test]
+    fn rejects_conflicting_options() {
+        let conflict = |args: &str| match parse(args) {
+            Err(CliError::Conflict(option, _)) => option,
+            _ => "",
+        };
+        assert_eq!(conflict("--inherit"), "--inherit");
+        assert_eq!(conflict("--chain 2 --layout a.ron"), "--chain");
+        assert_eq!(conflict("--headless --virtual --workers 2"), "--workers");
+        assert_eq!(
+            conflict("--headless --virtual --time-scale 2"),
+            "--time-scale"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Error, LockRecover};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Limites de [`Clock::set_scale`]: de câmera lenta a avanço rápido.
+pub const MIN_TIME_SCALE: f64 = 0.1;
+pub const MAX_TIME_SCALE: f64 = 20.0;
+
+/// Tempo simulado: o tempo de relógio multiplicado por uma escala. Mudar a
+/// escala só afeta daqui para frente, o tempo já passado não salta. Parado,
+/// o relógio só anda com [`Clock::advance`], como no passo a passo.
+pub struct Clock {
+    state: Mutex<ClockState>,
+}
+
+struct ClockState {
+    /// Instante da última mudança de escala e o tempo simulado nele.
+    anchor: Instant,
+    simulated: Duration,
+    scale: f64,
+    paused: bool,
+}
+
+impl Clock {
+    pub fn new() -> Clock {
+        Clock {
+            state: Mutex::new(ClockState {
+                anchor: Instant::now(),
+                simulated: Duration::ZERO,
+                scale: 1.0,
+                paused: false,
+            }),
+        }
+    }
+
+    /// Tempo simulado desde a criação do relógio.
+    pub fn now(&self) -> Duration {
+        let state = self.state.lock_recover();
+        if state.paused {
+            return state.simulated;
+        }
+        state.simulated + state.anchor.elapsed().mul_f64(state.scale)
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.state.lock_recover().scale
+    }
+
+    /// Falha fora de [`MIN_TIME_SCALE`]`..=`[`MAX_TIME_SCALE`].
+    pub fn set_scale(&self, scale: f64) -> Result<(), Error> {
+        if !(MIN_TIME_SCALE..=MAX_TIME_SCALE).contains(&scale) {
+            return Err(Error::InvalidTimeScale(scale));
+        }
+        let mut state = self.state.lock_recover();
+        state.settle();
+        state.scale = scale;
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock_recover().paused
+    }
+
+    pub fn pause(&self) {
+        let mut state = self.state.lock_recover();
+        state.settle();
+        state.paused = true;
+    }
+
+    pub fn resume(&self) {
+        let mut state = self.state.lock_recover();
+        state.settle();
+        state.paused = false;
+    }
+
+    /// Adianta o tempo simulado; feito para o relógio parado.
+    pub fn advance(&self, by: Duration) {
+        self.state.lock_recover().simulated += by;
+    }
+}
+
+impl ClockState {
+    /// Incorpora o tempo desde `anchor` e recomeça a contar de agora.
+    fn settle(&mut self) {
+        let now = Instant::now();
+        if !self.paused {
+            self.simulated += now.duration_since(self.anchor).mul_f64(self.scale);
+        }
+        self.anchor = now;
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new()
+    }
+}
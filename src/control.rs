@@ -0,0 +1,65 @@
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Pause,
+    Resume,
+    Stop,
+}
+
+/// O trem recebeu [`Command::Stop`] e a thread deve terminar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stopped;
+
+/// Lado do trem do canal de controle.
+pub struct TrainControl {
+    receiver: Receiver<Command>,
+    paused: bool,
+}
+
+impl TrainControl {
+    pub fn channel() -> (Sender<Command>, TrainControl) {
+        let (sender, receiver) = channel();
+        let control = TrainControl {
+            receiver,
+            paused: false,
+        };
+        (sender, control)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Aplica os comandos pendentes sem bloquear.
+    pub fn poll(&mut self) -> Result<(), Stopped> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(command) => self.apply(command)?,
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => return Err(Stopped),
+            }
+        }
+    }
+
+    /// Como [`TrainControl::poll`], mas bloqueia enquanto o trem estiver pausado.
+    pub fn checkpoint(&mut self) -> Result<(), Stopped> {
+        self.poll()?;
+        while self.paused {
+            match self.receiver.recv() {
+                Ok(command) => self.apply(command)?,
+                Err(_) => return Err(Stopped),
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, command: Command) -> Result<(), Stopped> {
+        match command {
+            Command::Pause => self.paused = true,
+            Command::Resume => self.paused = false,
+            Command::Stop => return Err(Stopped),
+        }
+        Ok(())
+    }
+}
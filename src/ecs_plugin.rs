@@ -0,0 +1,806 @@
+use bevy::prelude::*;
+use std::time::Duration;
+use tracks::{
+    circuit::{LockState, MutexStrategy, SyncStrategy},
+    track::TrainPosition,
+    Blocked, EventKind, LockRecover, Simulation, Step,
+};
+
+use crate::{keyboard_plugin::KeyBindings, TrainState, UiTrackPos};
+
+/// Modo `--ecs`: a simulação roda em sistemas do Bevy, sem threads. Cada trem
+/// é uma entidade com a sua [`Route`] e o seu [`Motion`], as travas dos
+/// trilhos são o recurso [`Locks`] e andar, liberar e obter trilhos são
+/// sistemas, todos no tempo do `Time` do Bevy ([`SimulationTime`]).
+pub struct EcsSimulationPlugin;
+
+/// Rota do trem, girada para o passo inicial, e o passo em que ele está.
+#[derive(Component)]
+struct Route {
+    train: usize,
+    steps: Vec<Vec<usize>>,
+    step: usize,
+}
+
+impl Route {
+    fn id(&self) -> u64 {
+        self.train as u64
+    }
+
+    /// Trilho `index` do passo atual.
+    fn segment(&self, index: usize) -> usize {
+        self.steps[self.step][index]
+    }
+}
+
+/// Em que ponto do passo atual o trem está.
+#[derive(Component)]
+enum Motion {
+    /// Vai declarar a região crítica do passo e pedir o primeiro trilho.
+    Entering,
+    /// Esperando o trilho `order[next]` do passo.
+    Acquiring { order: Vec<usize>, next: usize },
+    /// Percorrendo o trilho `index` do passo.
+    Running { index: usize, travelled: f64 },
+    /// Chegou ao fim do trilho `index` e andou `beyond` além dele; quem
+    /// libera o trilho é [`release_segments`].
+    Finished { index: usize, beyond: f64 },
+}
+
+/// Travas dos trilhos, com as mesmas regras do
+/// [`tracks::circuit::Interlocking`] das threads; sem mutex, porque só os
+/// sistemas deste plugin mexem nelas. O [`tracks::LockTracker`] e o
+/// [`tracks::EventLog`] da simulação acompanham cada pedido, entrega e
+/// liberação, então inspetor, cores dos trilhos e estatísticas não mudam.
+struct Locks {
+    state: LockState,
+    strategy: Box<dyn SyncStrategy>,
+}
+
+impl Locks {
+    fn new(simulation: &Simulation) -> Locks {
+        let mut state = LockState::new(simulation.layout.segments.len());
+        for (train, setup) in simulation.setups().iter().enumerate() {
+            state.priorities.insert(train as u64, setup.priority);
+        }
+        Locks {
+            state,
+            strategy: simulation.sync().build(&simulation.layout),
+        }
+    }
+
+    fn grantable(&self, simulation: &Simulation, train: u64, segment: usize) -> bool {
+        self.state
+            .grantable(&*self.strategy, simulation.grant_policy(), train, segment)
+    }
+}
+
+impl Default for Locks {
+    fn default() -> Self {
+        Locks {
+            state: LockState::new(0),
+            strategy: Box::new(MutexStrategy),
+        }
+    }
+}
+
+/// Segundos simulados neste quadro: os do `Time` do Bevy vezes a escala de
+/// tempo, nenhum no passo a passo. O relógio dos eventos fica parado e anda
+/// só isso, então trens, eventos e estatísticas contam o mesmo tempo.
+#[derive(Default)]
+struct SimulationTime {
+    delta: f64,
+}
+
+/// Passo a passo: o relógio fica parado e os trens só andam um acontecimento
+/// por vez, com a tecla de passo ou o botão do painel. Inserir
+/// `StepMode::new(true)` antes do plugin para começar assim.
+#[derive(Default)]
+pub struct StepMode {
+    pub active: bool,
+    /// Passos dados desde o início da simulação.
+    count: usize,
+    report: String,
+}
+
+impl StepMode {
+    pub fn new(active: bool) -> StepMode {
+        StepMode {
+            active,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Component)]
+struct StepPanel;
+
+#[derive(Component)]
+struct StepText;
+
+#[derive(Component)]
+struct StepButton;
+
+const STEP_BUTTON_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+
+impl Plugin for EcsSimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StepMode>()
+            .init_resource::<Locks>()
+            .init_resource::<SimulationTime>()
+            .add_startup_system(spawn_step_panel)
+            .add_system(respawn_trains)
+            .add_system(tick_time.after(respawn_trains))
+            .add_system(move_trains.after(tick_time))
+            .add_system(release_segments.after(move_trains))
+            .add_system(acquire_segments.after(release_segments))
+            .add_system(step_input.after(acquire_segments))
+            .add_system(place_trains.after(step_input))
+            .add_system(step_panel_update);
+    }
+}
+
+/// Na largada e sempre que a simulação é recomeçada ou troca de cenário, os
+/// trens e as travas antigos dão lugar aos do estado inicial.
+fn respawn_trains(
+    mut commands: Commands,
+    simulation: Res<Simulation>,
+    mut locks: ResMut<Locks>,
+    mut step_mode: ResMut<StepMode>,
+    trains: Query<Entity, With<Route>>,
+) {
+    if !simulation.is_changed() {
+        return;
+    }
+    trains.for_each(|entity| commands.entity(entity).despawn());
+    for (train, setup) in simulation.setups().iter().enumerate() {
+        // trens desligados nunca pedem trilhos
+        if !setup.enabled {
+            continue;
+        }
+        let mut steps = simulation.layout.route(train);
+        steps.rotate_left(setup.start_step);
+        commands
+            .spawn()
+            .insert(Route {
+                train,
+                steps,
+                step: 0,
+            })
+            .insert(Motion::Entering);
+    }
+    *locks = Locks::new(&simulation);
+
+    // o relógio é outro a cada simulação nova e só anda com tick_time
+    simulation.events.clock().pause();
+    step_mode.count = 0;
+    step_mode.report = "pronto para o primeiro passo".to_string();
+}
+
+fn tick_time(
+    time: Res<Time>,
+    simulation: Res<Simulation>,
+    step_mode: Res<StepMode>,
+    mut simulation_time: ResMut<SimulationTime>,
+) {
+    simulation_time.delta = if step_mode.active {
+        0.0
+    } else {
+        time.delta_seconds_f64() * simulation.time_scale()
+    };
+    simulation
+        .events
+        .clock()
+        .advance(Duration::from_secs_f64(simulation_time.delta));
+}
+
+fn move_trains(
+    simulation_time: Res<SimulationTime>,
+    simulation: Res<Simulation>,
+    mut trains: Query<(&Route, &mut Motion)>,
+) {
+    trains.for_each_mut(|(route, mut motion)| {
+        let Motion::Running { index, travelled } = *motion else {
+            return;
+        };
+        if simulation.is_paused(route.train) {
+            return;
+        }
+        let distance = simulation.tracks[route.segment(index)].distance;
+        let travelled = travelled + velocity(&simulation, route.train) * simulation_time.delta;
+        *motion = if travelled < distance {
+            Motion::Running { index, travelled }
+        } else {
+            Motion::Finished {
+                index,
+                beyond: travelled - distance,
+            }
+        };
+    });
+}
+
+fn release_segments(
+    simulation: Res<Simulation>,
+    mut locks: ResMut<Locks>,
+    mut trains: Query<(&mut Route, &mut Motion)>,
+) {
+    trains.for_each_mut(|(mut route, mut motion)| {
+        if let Motion::Finished { index, beyond } = *motion {
+            *motion = leave(&simulation, &mut locks, &mut route, index, beyond);
+        }
+    });
+}
+
+fn acquire_segments(
+    simulation: Res<Simulation>,
+    step_mode: Res<StepMode>,
+    mut locks: ResMut<Locks>,
+    mut trains: Query<(&mut Route, &mut Motion)>,
+) {
+    if step_mode.active {
+        return;
+    }
+    let mut trains: Vec<(Mut<Route>, Mut<Motion>)> = trains.iter_mut().collect();
+    trains.sort_by_key(|(route, _)| route.train);
+    while grant_one(&simulation, &mut locks, &mut trains).is_some() {}
+}
+
+fn velocity(simulation: &Simulation, train: usize) -> f64 {
+    simulation.trains[train].lock_recover().velocity
+}
+
+/// Segundos até o trem terminar o trilho em que está, na velocidade atual;
+/// `None` se ele não está andando.
+fn remaining(simulation: &Simulation, route: &Route, motion: &Motion) -> Option<f64> {
+    let Motion::Running { index, travelled } = *motion else {
+        return None;
+    };
+    let distance = simulation.tracks[route.segment(index)].distance;
+    let velocity = velocity(simulation, route.train);
+    (velocity > 0.0).then(|| (distance - travelled) / velocity)
+}
+
+fn request(simulation: &Simulation, locks: &mut Locks, train: u64, segment: usize) {
+    locks.state.enqueue(train, segment);
+    simulation
+        .events
+        .record(train, EventKind::Requested(segment));
+    simulation.tracker.waiting(train, segment);
+}
+
+fn enter(simulation: &Simulation, route: &Route, index: usize, travelled: f64) -> Motion {
+    simulation
+        .events
+        .record(route.id(), EventKind::Entered(route.segment(index)));
+    Motion::Running { index, travelled }
+}
+
+/// Libera o trilho `index` e entra no próximo da região crítica, que já é do
+/// trem, com o que ele andou além do fim, ou passa ao próximo passo da rota
+/// sem pedir nada ainda.
+fn leave(
+    simulation: &Simulation,
+    locks: &mut Locks,
+    route: &mut Route,
+    index: usize,
+    beyond: f64,
+) -> Motion {
+    let segment = route.segment(index);
+    if locks.state.release(route.id(), segment) {
+        simulation.tracker.released(route.id(), segment);
+        simulation
+            .events
+            .record(route.id(), EventKind::Released(segment));
+    }
+    if index + 1 < route.steps[route.step].len() {
+        enter(simulation, route, index + 1, beyond)
+    } else {
+        route.step = (route.step + 1) % route.steps.len();
+        Motion::Entering
+    }
+}
+
+/// Entrega um trilho ao primeiro trem de `trains`, que vem em ordem de trem,
+/// que pode obter o que espera, declarando antes a região crítica de quem
+/// chegou a um passo novo; ao obter o último trilho da região, o trem entra
+/// no primeiro. Devolve o trem que obteve o trilho.
+fn grant_one(
+    simulation: &Simulation,
+    locks: &mut Locks,
+    trains: &mut [(Mut<Route>, Mut<Motion>)],
+) -> Option<usize> {
+    locks.state.paused = trains
+        .iter()
+        .map(|(route, _)| route.train)
+        .filter(|train| simulation.is_paused(*train))
+        .map(|train| train as u64)
+        .collect();
+
+    for (route, motion) in trains.iter_mut() {
+        if simulation.is_paused(route.train) {
+            continue;
+        }
+        let id = route.id();
+        if let Motion::Entering = **motion {
+            let step = route.steps[route.step].clone();
+            let order = locks.strategy.order(&step);
+            locks.state.claims.insert(id, step);
+            request(simulation, locks, id, order[0]);
+            **motion = Motion::Acquiring { order, next: 0 };
+        }
+        let Motion::Acquiring { order, next } = &mut **motion else {
+            continue;
+        };
+        let segment = order[*next];
+        if !locks.grantable(simulation, id, segment) {
+            continue;
+        }
+
+        locks.state.grant(id, segment);
+        simulation.tracker.acquired(id, segment);
+        simulation.events.record(id, EventKind::Acquired(segment));
+        *next += 1;
+        match order.get(*next).copied() {
+            Some(segment) => request(simulation, locks, id, segment),
+            None => **motion = enter(simulation, route, 0, 0.0),
+        }
+        return Some(route.train);
+    }
+    None
+}
+
+/// Anda com todos os trens até o primeiro deles terminar o trilho, que ele
+/// libera; o próximo trilho que ele precisar pedir fica para outro passo.
+/// Quem empatar age depois.
+fn finish_track(
+    simulation: &Simulation,
+    locks: &mut Locks,
+    trains: &mut [(Mut<Route>, Mut<Motion>)],
+) -> Option<usize> {
+    let (first, elapsed) = trains
+        .iter()
+        .filter(|(route, _)| !simulation.is_paused(route.train))
+        .filter_map(|(route, motion)| Some((route.train, remaining(simulation, route, motion)?)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+    simulation
+        .events
+        .clock()
+        .advance(Duration::from_secs_f64(elapsed));
+    for (route, motion) in trains.iter_mut() {
+        let Motion::Running { index, travelled } = **motion else {
+            continue;
+        };
+        if simulation.is_paused(route.train) {
+            continue;
+        }
+        if route.train == first {
+            **motion = leave(simulation, locks, route, index, 0.0);
+        } else {
+            let distance = simulation.tracks[route.segment(index)].distance;
+            let travelled = travelled + velocity(simulation, route.train) * elapsed;
+            **motion = Motion::Running {
+                index,
+                travelled: travelled.min(distance),
+            };
+        }
+    }
+    Some(first)
+}
+
+/// Trens que não podem andar agora e o motivo, na ordem de `trains`.
+fn blocked(
+    simulation: &Simulation,
+    locks: &Locks,
+    trains: &[(Mut<Route>, Mut<Motion>)],
+) -> Vec<(usize, Blocked)> {
+    let policy = simulation.grant_policy();
+    let state = &locks.state;
+    trains
+        .iter()
+        .filter_map(|(route, motion)| {
+            let train = route.train;
+            if simulation.is_paused(train) {
+                return Some((train, Blocked::Paused));
+            }
+            let Motion::Acquiring { order, next } = &**motion else {
+                return None;
+            };
+            let segment = order[*next];
+            let eligible = |waiting| state.eligible(&*locks.strategy, waiting, segment);
+            let reason = match state.holders[segment] {
+                Some(holder) => Blocked::Held { segment, holder },
+                None if locks.grantable(simulation, route.id(), segment) => {
+                    Blocked::Ready { segment }
+                }
+                None => match policy.next(state, segment, eligible) {
+                    Some(next) if next != route.id() => Blocked::Behind { segment, next },
+                    _ => Blocked::Refused { segment },
+                },
+            };
+            Some((train, reason))
+        })
+        .collect()
+}
+
+/// Onde o trem está; esperando um trilho, ele fica no fim do último trilho
+/// do passo anterior.
+fn position(simulation: &Simulation, route: &Route, motion: &Motion) -> TrainPosition {
+    match *motion {
+        Motion::Running { index, travelled } => {
+            let track = route.segment(index);
+            TrainPosition {
+                track,
+                progress: travelled / simulation.tracks[track].distance,
+            }
+        }
+        Motion::Finished { index, .. } => TrainPosition {
+            track: route.segment(index),
+            progress: 1.0,
+        },
+        Motion::Entering | Motion::Acquiring { .. } => {
+            let previous = (route.step + route.steps.len() - 1) % route.steps.len();
+            let previous = &route.steps[previous];
+            TrainPosition {
+                track: previous[previous.len() - 1],
+                progress: 1.0,
+            }
+        }
+    }
+}
+
+fn place_trains(
+    ui_tracks: Res<UiTrackPos>,
+    simulation: Res<Simulation>,
+    trains: Query<(&Route, &Motion)>,
+    mut sprites: Query<(&TrainState, &mut Transform)>,
+) {
+    let mut positions = vec![None; simulation.trains.len()];
+    trains.for_each(|(route, motion)| {
+        positions[route.train] = Some(position(&simulation, route, motion));
+    });
+    sprites.for_each_mut(|(train, mut transform)| {
+        let Some(position) = &positions[train.circuit] else {
+            return;
+        };
+        let path = &ui_tracks.loops[train.circuit];
+        if let Some(point) = path.point_at(position.track, position.progress as f32) {
+            transform.translation = point.extend(transform.translation.z);
+        }
+    });
+}
+
+type StepButtons<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut UiColor),
+    (Changed<Interaction>, With<StepButton>),
+>;
+
+fn step_input(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    simulation: Res<Simulation>,
+    mut step_mode: ResMut<StepMode>,
+    mut locks: ResMut<Locks>,
+    mut trains: Query<(&mut Route, &mut Motion)>,
+    mut buttons: StepButtons,
+) {
+    if keys.just_pressed(bindings.step_mode) {
+        step_mode.active = !step_mode.active;
+    }
+
+    let mut pressed = step_mode.active && keys.just_pressed(bindings.step);
+    buttons.for_each_mut(|(interaction, mut color)| match *interaction {
+        Interaction::Clicked => {
+            *color = UiColor(Color::rgb(0.35, 0.75, 0.35));
+            pressed = true;
+        }
+        Interaction::Hovered | Interaction::None => *color = UiColor(STEP_BUTTON_COLOR),
+    });
+    if !pressed {
+        return;
+    }
+
+    let mut trains: Vec<(Mut<Route>, Mut<Motion>)> = trains.iter_mut().collect();
+    trains.sort_by_key(|(route, _)| route.train);
+    step_mode.report = match step(&simulation, &mut locks, &mut trains) {
+        Ok(step) => {
+            step_mode.count += 1;
+            describe_step(&simulation, step_mode.count, &step)
+        }
+        Err(blocked) => format!(
+            "nenhum trem pode andar\n{}",
+            describe_blocked(&simulation, &blocked)
+        ),
+    };
+}
+
+/// Um acontecimento, com as regras de [`tracks::stepper::step`]: uma entrega
+/// ou, se ninguém pode obter o que espera, uma liberação. Se nenhum trem
+/// pode agir, devolve quem está esperando e por quê.
+fn step(
+    simulation: &Simulation,
+    locks: &mut Locks,
+    trains: &mut [(Mut<Route>, Mut<Motion>)],
+) -> Result<Step, Vec<(usize, Blocked)>> {
+    let from = simulation.events.count();
+    let train =
+        grant_one(simulation, locks, trains).or_else(|| finish_track(simulation, locks, trains));
+    let blocked = blocked(simulation, locks, trains);
+    match train {
+        Some(train) => Ok(Step {
+            train,
+            time: simulation.events.elapsed(),
+            events: simulation.events.events_since(from),
+            blocked,
+        }),
+        None => Err(blocked),
+    }
+}
+
+fn describe_step(simulation: &Simulation, count: usize, step: &Step) -> String {
+    let layout = &simulation.layout;
+    let segment = |segment: usize| layout.segments[segment].name.as_str();
+    let mut lines = vec![format!(
+        "passo {} em {:.2}s: {} agiu",
+        count,
+        step.time.as_secs_f64(),
+        layout.circuits[step.train].name
+    )];
+    for event in &step.events {
+        let action = match event.kind {
+            EventKind::Requested(s) => format!("pediu {}", segment(s)),
+            EventKind::Acquired(s) => format!("obteve {}", segment(s)),
+            EventKind::Released(s) => format!("liberou {}", segment(s)),
+            EventKind::Entered(s) => format!("entrou em {}", segment(s)),
+            EventKind::VelocityChanged(velocity) => format!("mudou para {:.1}", velocity),
+        };
+        let train = &layout.circuits[event.train as usize].name;
+        lines.push(format!("  {} {}", train, action));
+    }
+    lines.push(describe_blocked(simulation, &step.blocked));
+    lines.join("\n")
+}
+
+/// Um trem por linha, com o trilho que espera e quem está com ele.
+fn describe_blocked(simulation: &Simulation, blocked: &[(usize, Blocked)]) -> String {
+    let layout = &simulation.layout;
+    if blocked.is_empty() {
+        return "ninguém esperando".to_string();
+    }
+    let mut lines = vec!["esperando:".to_string()];
+    for (train, reason) in blocked {
+        let reason = match reason {
+            Blocked::Held { segment, holder } => format!(
+                "{} está com {}",
+                layout.segments[*segment].name, layout.circuits[*holder as usize].name
+            ),
+            Blocked::Refused { segment } => format!(
+                "{} está livre, mas a estratégia não libera",
+                layout.segments[*segment].name
+            ),
+            Blocked::Behind { segment, next } => format!(
+                "{} está livre, mas é a vez de {}",
+                layout.segments[*segment].name, layout.circuits[*next as usize].name
+            ),
+            Blocked::Ready { segment } => format!(
+                "{} ficou livre, obtém no próximo passo",
+                layout.segments[*segment].name
+            ),
+            Blocked::Paused => "pausado".to_string(),
+        };
+        lines.push(format!("  {}: {}", layout.circuits[*train].name, reason));
+    }
+    lines.join("\n")
+}
+
+fn spawn_step_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // no canto de baixo, à esquerda, só enquanto o passo a passo está ligado
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                padding: Rect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                display: Display::None,
+                ..Default::default()
+            },
+            color: UiColor(Color::rgba(1.0, 1.0, 1.0, 0.9)),
+            ..Default::default()
+        })
+        .insert(StepPanel)
+        .with_children(|panel| {
+            panel
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 14.0,
+                            color: Color::BLACK,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(StepText);
+
+            panel
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(150.0), Val::Px(30.0)),
+                        margin: Rect {
+                            top: Val::Px(8.0),
+                            ..Default::default()
+                        },
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    color: UiColor(STEP_BUTTON_COLOR),
+                    ..Default::default()
+                })
+                .insert(StepButton)
+                .with_children(|button| {
+                    button.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Próximo passo",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 16.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                            Default::default(),
+                        ),
+                        ..Default::default()
+                    });
+                });
+        });
+}
+
+fn step_panel_update(
+    step_mode: Res<StepMode>,
+    mut panels: Query<&mut Style, With<StepPanel>>,
+    mut texts: Query<&mut Text, With<StepText>>,
+) {
+    if !step_mode.is_changed() {
+        return;
+    }
+    panels.for_each_mut(|mut style| {
+        style.display = if step_mode.active {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    });
+    texts.for_each_mut(|mut text| {
+        text.sections[0].value = step_mode.report.clone();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracks::{
+        circuit::{Layout, SyncKind},
+        stepper, Executor,
+    };
+
+    const STRATEGIES: [SyncKind; 4] = [
+        SyncKind::Mutex,
+        SyncKind::Semaphore,
+        SyncKind::Banker,
+        SyncKind::Ordered,
+    ];
+
+    fn simulation(sync: SyncKind) -> Simulation {
+        let layout = Layout::from_ron(include_str!("../assets/layouts/four_trains.ron")).unwrap();
+        let mut simulation = Simulation::new(layout, sync, &[1.0, 2.0, 3.0, 4.0]).unwrap();
+        simulation.set_executor(Executor::Stepped);
+        simulation
+    }
+
+    /// Só os recursos que os sistemas dos trens usam, com os trens já criados.
+    fn world(sync: SyncKind) -> World {
+        let mut world = World::new();
+        world.insert_resource(simulation(sync));
+        world.init_resource::<Locks>();
+        world.init_resource::<SimulationTime>();
+        world.init_resource::<StepMode>();
+        SystemStage::single(respawn_trains).run(&mut world);
+        world
+    }
+
+    type Summary = (usize, Vec<(u64, EventKind)>, Vec<(usize, Blocked)>);
+
+    /// O passo sem os instantes, que dependem de quando cada relógio parou.
+    fn summary(step: &Step) -> Summary {
+        let events = step
+            .events
+            .iter()
+            .map(|event| (event.train, event.kind.clone()))
+            .collect();
+        (step.train, events, step.blocked.clone())
+    }
+
+    fn ecs_steps(sync: SyncKind, count: usize) -> Vec<Option<Summary>> {
+        let mut world = world(sync);
+        let mut query = world.query::<(&mut Route, &mut Motion)>();
+        (0..count)
+            .map(|_| {
+                world.resource_scope(|world, simulation: Mut<Simulation>| {
+                    world.resource_scope(|world, mut locks: Mut<Locks>| {
+                        let mut trains: Vec<(Mut<Route>, Mut<Motion>)> =
+                            query.iter_mut(world).collect();
+                        trains.sort_by_key(|(route, _)| route.train);
+                        step(&simulation, &mut locks, &mut trains)
+                            .ok()
+                            .map(|step| summary(&step))
+                    })
+                })
+            })
+            .collect()
+    }
+
+    fn library_steps(sync: SyncKind, count: usize) -> Vec<Option<Summary>> {
+        let simulation = simulation(sync);
+        simulation.events.clock().pause();
+        let mut machines = simulation.machines();
+        let network = simulation.network();
+        let is_paused = |train| simulation.is_paused(train);
+        (0..count)
+            .map(|_| stepper::step(&mut machines, &network, is_paused).map(|step| summary(&step)))
+            .collect()
+    }
+
+    #[test]
+    fn steps_follow_the_library_stepper() {
+        for sync in STRATEGIES {
+            let steps = ecs_steps(sync, 200);
+            assert!(steps.iter().all(Option::is_some), "{:?}", sync);
+            assert_eq!(steps, library_steps(sync, 200), "{:?}", sync);
+        }
+    }
+
+    #[test]
+    fn frames_keep_each_track_with_one_train() {
+        for sync in STRATEGIES {
+            let mut world = world(sync);
+            let mut stage = SystemStage::single_threaded()
+                .with_system(move_trains)
+                .with_system(release_segments.after(move_trains))
+                .with_system(acquire_segments.after(release_segments));
+            for _ in 0..2000 {
+                world.resource_mut::<SimulationTime>().delta = 0.05;
+                let simulation = world.resource::<Simulation>();
+                simulation.events.clock().advance(Duration::from_millis(50));
+                stage.run(&mut world);
+            }
+
+            let simulation = world.resource::<Simulation>();
+            let mut holders = vec![None; simulation.layout.segments.len()];
+            let mut entered = vec![0; simulation.trains.len()];
+            for event in simulation.events.events() {
+                match event.kind {
+                    EventKind::Acquired(segment) => {
+                        assert_eq!(holders[segment], None, "{:?}", sync);
+                        holders[segment] = Some(event.train);
+                    }
+                    EventKind::Released(segment) => {
+                        assert_eq!(holders[segment], Some(event.train), "{:?}", sync);
+                        holders[segment] = None;
+                    }
+                    EventKind::Entered(_) => entered[event.train as usize] += 1,
+                    _ => {}
+                }
+            }
+            assert!(entered.iter().all(|count| *count > 5), "{:?}", sync);
+        }
+    }
+}
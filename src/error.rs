@@ -0,0 +1,126 @@
+use crate::{circuit::LayoutError, ScenarioError, MAX_TIME_SCALE, MIN_TIME_SCALE};
+use std::{
+    fmt,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+/// Erros da biblioteca: valores fora dos limites, referências a trilhos ou
+/// trens que não existem e falhas ao carregar malhas e cenários.
+#[derive(Debug)]
+pub enum Error {
+    /// Velocidade fora de `min..=max` (ou não finita); o trem fica como estava.
+    InvalidVelocity {
+        train: u64,
+        velocity: f64,
+        min: f64,
+        max: f64,
+    },
+    InvalidVelocityLimits {
+        train: u64,
+        min: f64,
+        max: f64,
+    },
+    InvalidDistance {
+        track: String,
+        distance: f64,
+    },
+    UnknownSegment {
+        circuit: String,
+        segment: String,
+    },
+    UnknownStep {
+        circuit: String,
+        step: usize,
+    },
+    UnknownTrain(usize),
+    /// Escala de tempo fora de [`crate::MIN_TIME_SCALE`]`..=`[`crate::MAX_TIME_SCALE`].
+    InvalidTimeScale(f64),
+    /// Uma configuração por trem é esperada, ver [`crate::TrainSetup`].
+    WrongTrainCount {
+        expected: usize,
+        found: usize,
+    },
+    Layout(LayoutError),
+    Scenario(ScenarioError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidVelocity {
+                train,
+                velocity,
+                min,
+                max,
+            } => write!(
+                f,
+                "velocity {} of train {} is outside {}..={}",
+                velocity, train, min, max
+            ),
+            Error::InvalidVelocityLimits { train, min, max } => write!(
+                f,
+                "train {} needs 0 <= min_velocity <= max_velocity, got {} and {}",
+                train, min, max
+            ),
+            Error::InvalidDistance { track, distance } => write!(
+                f,
+                "track {} needs a positive distance, got {}",
+                track, distance
+            ),
+            Error::UnknownSegment { circuit, segment } => {
+                write!(f, "circuit {} uses unknown segment {}", circuit, segment)
+            }
+            Error::UnknownStep { circuit, step } => {
+                write!(f, "circuit {} has no step {}", circuit, step)
+            }
+            Error::UnknownTrain(train) => write!(f, "there is no train {}", train),
+            Error::InvalidTimeScale(scale) => write!(
+                f,
+                "time scale {} is outside {}..={}",
+                scale, MIN_TIME_SCALE, MAX_TIME_SCALE
+            ),
+            Error::WrongTrainCount { expected, found } => write!(
+                f,
+                "expected a setup for each of the {} trains, got {}",
+                expected, found
+            ),
+            Error::Layout(error) => write!(f, "{}", error),
+            Error::Scenario(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Layout(error) => Some(error),
+            Error::Scenario(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<LayoutError> for Error {
+    fn from(error: LayoutError) -> Self {
+        Error::Layout(error)
+    }
+}
+
+impl From<ScenarioError> for Error {
+    fn from(error: ScenarioError) -> Self {
+        Error::Scenario(error)
+    }
+}
+
+/// Trava um mutex mesmo envenenado. Os dados protegidos (velocidades,
+/// posições, a tabela de locks) continuam coerentes se uma thread entrar em
+/// pânico com o lock, então os outros trens seguem em vez de propagar o pânico.
+pub trait LockRecover<T> {
+    fn lock_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockRecover<T> for Mutex<T> {
+    fn lock_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
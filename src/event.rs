@@ -0,0 +1,226 @@
+use crate::{Clock, LockRecover};
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::Duration,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventKind {
+    Entered(usize),
+    Requested(usize),
+    Acquired(usize),
+    Released(usize),
+    VelocityChanged(f64),
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Entered(_) => "entered",
+            EventKind::Requested(_) => "requested",
+            EventKind::Acquired(_) => "acquired",
+            EventKind::Released(_) => "released",
+            EventKind::VelocityChanged(_) => "velocity_changed",
+        }
+    }
+
+    pub fn segment(&self) -> Option<usize> {
+        match self {
+            EventKind::Entered(segment)
+            | EventKind::Requested(segment)
+            | EventKind::Acquired(segment)
+            | EventKind::Released(segment) => Some(*segment),
+            EventKind::VelocityChanged(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub time: Duration,
+    pub train: u64,
+    pub kind: EventKind,
+}
+
+/// Linha exportada: o evento com o nome do trilho no lugar do índice.
+#[derive(Serialize)]
+struct Record<'a> {
+    time: f64,
+    train: u64,
+    event: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    segment: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    velocity: Option<f64>,
+}
+
+/// Registro com carimbo de tempo de tudo o que os trens fizeram, no tempo
+/// simulado do seu [`Clock`].
+pub struct EventLog {
+    segments: Vec<String>,
+    clock: Clock,
+    events: Mutex<Vec<Event>>,
+}
+
+impl EventLog {
+    pub fn new(segments: Vec<String>) -> EventLog {
+        EventLog {
+            segments,
+            clock: Clock::new(),
+            events: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registra o evento no tempo simulado desde a criação do registro.
+    pub fn record(&self, train: u64, kind: EventKind) {
+        self.record_at(self.clock.now(), train, kind);
+    }
+
+    pub fn record_at(&self, time: Duration, train: u64, kind: EventKind) {
+        self.events.lock_recover().push(Event { time, train, kind });
+    }
+
+    /// Tempo simulado desde a criação do registro.
+    pub fn elapsed(&self) -> Duration {
+        self.clock.now()
+    }
+
+    /// Relógio dos trens que registram aqui; a sua escala acelera ou
+    /// desacelera a simulação inteira.
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Quantos eventos já foram registrados.
+    pub fn count(&self) -> usize {
+        self.events.lock_recover().len()
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.events.lock_recover().clone()
+    }
+
+    /// Eventos registrados a partir da posição `from`.
+    pub fn events_since(&self, from: usize) -> Vec<Event> {
+        let events = self.events.lock_recover();
+        events
+            .get(from..)
+            .map(<[Event]>::to_vec)
+            .unwrap_or_default()
+    }
+
+    pub fn write_jsonl<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for event in self.events.lock_recover().iter() {
+            serde_json::to_writer(&mut writer, &self.to_record(event))?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "time,train,event,segment,velocity")?;
+        for event in self.events.lock_recover().iter() {
+            let record = self.to_record(event);
+            writeln!(
+                writer,
+                "{},{},{},{},{}",
+                record.time,
+                record.train,
+                record.event,
+                record.segment.map(csv_field).unwrap_or_default(),
+                record.velocity.map(|v| v.to_string()).unwrap_or_default(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Exporta em CSV se o arquivo terminar em `.csv`, senão em JSON Lines.
+    pub fn export<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut writer = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => self.write_csv(&mut writer)?,
+            _ => self.write_jsonl(&mut writer)?,
+        }
+        writer.flush()
+    }
+
+    fn to_record(&self, event: &Event) -> Record<'_> {
+        Record {
+            time: event.time.as_secs_f64(),
+            train: event.train,
+            event: event.kind.name(),
+            segment: event
+                .kind
+                .segment()
+                .map(|segment| self.segments[segment].as_str()),
+            velocity: match event.kind {
+                EventKind::VelocityChanged(velocity) => Some(velocity),
+                _ => None,
+            },
+        }
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log() -> EventLog {
+        let log = EventLog::new(vec!["L1".to_string(), "Ponte, \"norte\"".to_string()]);
+        log.record_at(Duration::from_millis(1500), 0, EventKind::Acquired(0));
+        log.record_at(Duration::from_secs(2), 1, EventKind::Requested(1));
+        log.record_at(Duration::from_secs(3), 0, EventKind::VelocityChanged(2.5));
+        log
+    }
+
+    fn written<F>(write: F) -> String
+    where
+        F: FnOnce(&mut Vec<u8>) -> io::Result<()>,
+    {
+        let mut buffer = Vec::new();
+        write(&mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn csv_has_a_header_and_one_row_per_event() {
+        let csv = written(|buffer| log().write_csv(buffer));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "time,train,event,segment,velocity",
+                "1.5,0,acquired,L1,",
+                "2,1,requested,\"Ponte, \"\"norte\"\"\",",
+                "3,0,velocity_changed,,2.5",
+            ]
+        );
+    }
+
+    #[test]
+    fn jsonl_skips_fields_the_event_does_not_have() {
+        let jsonl = written(|buffer| log().write_jsonl(buffer));
+        let lines: Vec<&str> = jsonl.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                r#"{"time":1.5,"train":0,"event":"acquired","segment":"L1"}"#,
+                r#"{"time":2.0,"train":1,"event":"requested","segment":"Ponte, \"norte\""}"#,
+                r#"{"time":3.0,"train":0,"event":"velocity_changed","velocity":2.5}"#,
+            ]
+        );
+    }
+}
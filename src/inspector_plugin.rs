@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+use tracks::Simulation;
+
+use crate::{HalfWindowSize, UiTrackPos};
+
+/// Distância máxima do cursor até o contorno para considerar o trilho apontado.
+const PICK_DISTANCE: f32 = 15.0;
+
+pub struct InspectorPlugin;
+
+/// Trilho sob o cursor e trilho fixado por um clique.
+#[derive(Default)]
+struct Inspector {
+    hovered: Option<usize>,
+    pinned: Option<usize>,
+}
+
+#[derive(Component)]
+struct InspectorPanel;
+
+#[derive(Component)]
+struct InspectorText;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inspector>()
+            .add_startup_system(spawn_inspector)
+            .add_system(pick_segment)
+            .add_system(inspector_update);
+    }
+}
+
+fn spawn_inspector(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                padding: Rect::all(Val::Px(6.0)),
+                ..Default::default()
+            },
+            color: UiColor(Color::NONE),
+            ..Default::default()
+        })
+        .insert(InspectorPanel)
+        .with_children(|panel| {
+            panel
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 14.0,
+                            color: Color::BLACK,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(InspectorText);
+        });
+}
+
+/// Trilho mais próximo do cursor, procurando em todos os contornos.
+fn segment_at(ui_tracks: &UiTrackPos, simulation: &Simulation, point: Vec2) -> Option<usize> {
+    (0..simulation.layout.segments.len())
+        .filter_map(|segment| {
+            let distance = ui_tracks
+                .loops
+                .iter()
+                .filter_map(|path| path.distance_to(segment, point))
+                .reduce(f32::min)?;
+            Some((segment, distance))
+        })
+        .filter(|(_, distance)| *distance <= PICK_DISTANCE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(segment, _)| segment)
+}
+
+fn pick_segment(
+    windows: Res<Windows>,
+    mouse: Res<Input<MouseButton>>,
+    half_window_size: Res<HalfWindowSize>,
+    ui_tracks: Res<UiTrackPos>,
+    simulation: Res<Simulation>,
+    mut inspector: ResMut<Inspector>,
+) {
+    let cursor = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position());
+
+    // o cursor conta a partir do canto inferior esquerdo, a câmera do centro
+    let hovered = cursor.and_then(|cursor| {
+        let point = cursor - Vec2::new(half_window_size.width, half_window_size.height);
+        segment_at(&ui_tracks, &simulation, point)
+    });
+    inspector.hovered = hovered;
+
+    // clicar num trilho fixa o painel nele; clicar fora solta
+    if mouse.just_pressed(MouseButton::Left) && cursor.is_some() {
+        inspector.pinned = hovered;
+    }
+}
+
+fn describe(simulation: &Simulation, segment: usize) -> String {
+    let layout = &simulation.layout;
+    let name = |train: u64| layout.circuits[train as usize].name.clone();
+    let list = |trains: Vec<String>| {
+        if trains.is_empty() {
+            "-".to_string()
+        } else {
+            trains.join(", ")
+        }
+    };
+
+    let users = layout
+        .users(segment)
+        .into_iter()
+        .map(|circuit| layout.circuits[circuit].name.clone())
+        .collect();
+    let holder = simulation
+        .tracker
+        .holder(segment)
+        .map(name)
+        .unwrap_or_else(|| "livre".to_string());
+    let waiting = simulation
+        .tracker
+        .waiting_for(segment)
+        .into_iter()
+        .map(name)
+        .collect();
+
+    format!(
+        "{}\ndistância: {}\nusado por: {}\ntravado por: {}\nesperando: {}",
+        layout.segments[segment].name,
+        layout.segments[segment].distance,
+        list(users),
+        holder,
+        list(waiting)
+    )
+}
+
+fn inspector_update(
+    windows: Res<Windows>,
+    inspector: Res<Inspector>,
+    simulation: Res<Simulation>,
+    mut panel_query: Query<(&mut Style, &mut UiColor), With<InspectorPanel>>,
+    mut text_query: Query<&mut Text, With<InspectorText>>,
+) {
+    let segment = inspector.hovered.or(inspector.pinned);
+    let value = segment
+        .map(|segment| describe(&simulation, segment))
+        .unwrap_or_default();
+
+    let cursor = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position());
+    panel_query.for_each_mut(|(mut style, mut color)| {
+        if segment.is_none() {
+            *color = UiColor(Color::NONE);
+            return;
+        }
+        *color = UiColor(Color::rgba(1.0, 1.0, 1.0, 0.85));
+        // o painel segue o cursor enquanto nenhum trilho está fixado
+        if let (Some(cursor), None) = (cursor, inspector.pinned) {
+            style.position.left = Val::Px(cursor.x + 15.0);
+            style.position.bottom = Val::Px(cursor.y + 15.0);
+        }
+    });
+    text_query.for_each_mut(|mut text| {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    });
+}
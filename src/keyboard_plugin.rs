@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+use tracks::{Executor, Simulation};
+
+use crate::TrainRow;
+
+pub struct KeyboardPlugin;
+
+/// Teclas de cada comando. O plugin só insere o padrão se o app ainda não
+/// tiver um `KeyBindings`, então basta inserir outro antes para trocá-las.
+pub struct KeyBindings {
+    /// Tecla de cada trem, na ordem da malha.
+    pub select: Vec<KeyCode>,
+    pub next_train: KeyCode,
+    pub faster: KeyCode,
+    pub slower: KeyCode,
+    pub pause_train: KeyCode,
+    pub pause_all: KeyCode,
+    pub restart: KeyCode,
+    /// Liga e desliga o passo a passo; só no modo `--ecs`.
+    pub step_mode: KeyCode,
+    pub step: KeyCode,
+    pub help: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            select: vec![
+                KeyCode::Key1,
+                KeyCode::Key2,
+                KeyCode::Key3,
+                KeyCode::Key4,
+                KeyCode::Key5,
+                KeyCode::Key6,
+                KeyCode::Key7,
+                KeyCode::Key8,
+                KeyCode::Key9,
+                KeyCode::Key0,
+            ],
+            next_train: KeyCode::Tab,
+            faster: KeyCode::Up,
+            slower: KeyCode::Down,
+            pause_train: KeyCode::P,
+            pause_all: KeyCode::Space,
+            restart: KeyCode::R,
+            step_mode: KeyCode::M,
+            step: KeyCode::N,
+            help: KeyCode::H,
+        }
+    }
+}
+
+/// Trem que recebe os comandos do teclado.
+#[derive(Default)]
+pub struct SelectedTrain(pub usize);
+
+#[derive(Component)]
+struct HelpOverlay;
+
+const SELECTED_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.15);
+
+impl Plugin for KeyboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KeyBindings>()
+            .init_resource::<SelectedTrain>()
+            .add_startup_system(spawn_help)
+            .add_system(keyboard_input)
+            .add_system(highlight_selected);
+    }
+}
+
+fn key_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    match key {
+        KeyCode::Space => "Espaço".to_string(),
+        KeyCode::Up => "Seta cima".to_string(),
+        KeyCode::Down => "Seta baixo".to_string(),
+        KeyCode::Left => "Seta esquerda".to_string(),
+        KeyCode::Right => "Seta direita".to_string(),
+        _ => name.strip_prefix("Key").unwrap_or(&name).to_string(),
+    }
+}
+
+fn help_text(bindings: &KeyBindings, simulation: &Simulation) -> String {
+    let mut lines: Vec<String> = bindings
+        .select
+        .iter()
+        .zip(&simulation.layout.circuits)
+        .map(|(key, circuit)| format!("{:>13}  seleciona {}", key_name(*key), circuit.name))
+        .collect();
+    lines.extend(
+        [
+            (bindings.next_train, "seleciona o próximo trem"),
+            (bindings.faster, "acelera o trem"),
+            (bindings.slower, "freia o trem"),
+            (bindings.pause_train, "pausa/segue o trem"),
+            (bindings.pause_all, "pausa/segue todos"),
+            (bindings.restart, "reinicia a simulação"),
+        ]
+        .map(|(key, action)| format!("{:>13}  {}", key_name(key), action)),
+    );
+    if simulation.executor() == Executor::Stepped {
+        lines.extend(
+            [
+                (bindings.step_mode, "liga/desliga o passo a passo"),
+                (bindings.step, "dá um passo"),
+            ]
+            .map(|(key, action)| format!("{:>13}  {}", key_name(key), action)),
+        );
+    }
+    lines.push(format!(
+        "{:>13}  mostra/esconde esta ajuda",
+        key_name(bindings.help)
+    ));
+    lines.join("\n")
+}
+
+fn spawn_help(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bindings: Res<KeyBindings>,
+    simulation: Res<Simulation>,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(20.0),
+                    top: Val::Px(20.0),
+                    ..Default::default()
+                },
+                padding: Rect::all(Val::Px(10.0)),
+                display: Display::None,
+                ..Default::default()
+            },
+            color: UiColor(Color::rgba(1.0, 1.0, 1.0, 0.9)),
+            ..Default::default()
+        })
+        .insert(HelpOverlay)
+        .with_children(|overlay| {
+            overlay.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    help_text(&bindings, &simulation),
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 14.0,
+                        color: Color::BLACK,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        });
+}
+
+fn keyboard_input(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut selected: ResMut<SelectedTrain>,
+    mut simulation: ResMut<Simulation>,
+    mut help_query: Query<&mut Style, With<HelpOverlay>>,
+) {
+    let trains = simulation.trains.len();
+    if let Some(train) = bindings
+        .select
+        .iter()
+        .position(|key| keys.just_pressed(*key))
+        .filter(|train| *train < trains)
+    {
+        selected.0 = train;
+    }
+    if keys.just_pressed(bindings.next_train) {
+        selected.0 = (selected.0 + 1) % trains;
+    }
+
+    let train = selected.0;
+    let mut results = Vec::new();
+    if keys.just_pressed(bindings.faster) {
+        results.push(simulation.increment(train));
+    }
+    if keys.just_pressed(bindings.slower) {
+        results.push(simulation.decrement(train));
+    }
+    if keys.just_pressed(bindings.pause_train) {
+        if simulation.is_paused(train) {
+            results.push(simulation.resume(train));
+        } else {
+            results.push(simulation.pause(train));
+        }
+    }
+    if keys.just_pressed(bindings.pause_all) {
+        if (0..trains).all(|i| simulation.is_paused(i)) {
+            simulation.resume_all();
+        } else {
+            simulation.pause_all();
+        }
+    }
+    if keys.just_pressed(bindings.restart) {
+        results.push(simulation.restart());
+    }
+    for error in results.into_iter().filter_map(Result::err) {
+        warn!("{}", error);
+    }
+
+    if keys.just_pressed(bindings.help) {
+        help_query.for_each_mut(|mut style| {
+            style.display = match style.display {
+                Display::None => Display::Flex,
+                Display::Flex => Display::None,
+            };
+        });
+    }
+}
+
+/// Destaca a linha de botões do trem selecionado.
+fn highlight_selected(selected: Res<SelectedTrain>, mut query: Query<(&TrainRow, &mut UiColor)>) {
+    if !selected.is_changed() {
+        return;
+    }
+    query.for_each_mut(|(row, mut color)| {
+        *color = UiColor(if row.0 == selected.0 {
+            SELECTED_COLOR
+        } else {
+            Color::NONE
+        });
+    });
+}
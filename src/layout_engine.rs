@@ -0,0 +1,657 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use tracks::circuit::Layout;
+
+use crate::loop_path::LoopPath;
+
+/// Posição de um circuito na grade: a linha e as colunas que ele ocupa.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Placement {
+    row: i32,
+    first_column: i32,
+    last_column: i32,
+}
+
+impl Placement {
+    fn cells(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        (self.first_column..=self.last_column).map(move |column| (self.row, column))
+    }
+}
+
+/// Contornos, rótulos e tamanho da célula calculados para uma área da janela.
+pub struct TrackDrawing {
+    pub loops: Vec<LoopPath>,
+    pub labels: Vec<(Vec2, String)>,
+    pub cell: Vec2,
+}
+
+/// Distribui os circuitos em `area_min..area_max`. Circuitos que dividem
+/// trilhos ficam encostados, e os trilhos divididos vão para a aresta comum.
+pub fn compute(layout: &Layout, area_min: Vec2, area_max: Vec2) -> TrackDrawing {
+    let routes: Vec<Vec<usize>> = (0..layout.circuits.len())
+        .map(|circuit| layout.route(circuit).concat())
+        .collect();
+    let placements = place(&routes);
+
+    let columns = placements
+        .iter()
+        .map(|p| p.last_column + 1)
+        .max()
+        .unwrap_or(1);
+    let rows = placements.iter().map(|p| p.row + 1).max().unwrap_or(1);
+    let area = area_max - area_min;
+    let cell = Vec2::new(area.x / columns as f32, area.y / rows as f32);
+
+    let loops: Vec<LoopPath> = placements
+        .iter()
+        .enumerate()
+        .map(|(circuit, placement)| {
+            let span = (placement.last_column - placement.first_column + 1) as f32;
+            let size = Vec2::new(span * cell.x, cell.y);
+            let center = Vec2::new(
+                area_min.x + placement.first_column as f32 * cell.x + size.x / 2.0,
+                area_max.y - (placement.row as f32 + 0.5) * cell.y,
+            );
+            let (sides, clockwise) = outline(&routes, &placements, circuit);
+            LoopPath::new(center, size, sides, clockwise)
+        })
+        .collect();
+
+    let labels = labels(layout, &routes, &loops, cell);
+    TrackDrawing {
+        loops,
+        labels,
+        cell,
+    }
+}
+
+fn shares_track(a: &[usize], b: &[usize]) -> bool {
+    a.iter().any(|track| b.contains(track))
+}
+
+fn single(row: i32, column: i32) -> Placement {
+    Placement {
+        row,
+        first_column: column,
+        last_column: column,
+    }
+}
+
+/// Coloca os circuitos um a um, sempre o que tem menos vizinhos já colocados:
+/// assim as cadeias crescem numa linha e quem encosta em vários circuitos vem
+/// depois, ocupando as colunas de todos eles na linha de baixo.
+fn place(routes: &[Vec<usize>]) -> Vec<Placement> {
+    let count = routes.len();
+    let max_columns = ((count as f32).sqrt().ceil() as i32).max(3);
+    let neighbours: Vec<Vec<usize>> = (0..count)
+        .map(|a| {
+            (0..count)
+                .filter(|&b| a != b && shares_track(&routes[a], &routes[b]))
+                .collect()
+        })
+        .collect();
+
+    let mut placements: Vec<Option<Placement>> = vec![None; count];
+    let mut occupied: HashMap<(i32, i32), usize> = HashMap::new();
+
+    loop {
+        let placed = |circuit: usize| -> Vec<Placement> {
+            neighbours[circuit]
+                .iter()
+                .filter_map(|&other| placements[other])
+                .collect()
+        };
+
+        let next = (0..count)
+            .filter(|&circuit| placements[circuit].is_none())
+            .min_by_key(|&circuit| match placed(circuit).len() {
+                // circuitos soltos só depois dos que continuam a malha
+                0 => usize::MAX,
+                placed => placed,
+            });
+        let Some(circuit) = next else {
+            break;
+        };
+
+        let fits = |placement: &Placement| {
+            placement.first_column >= 0
+                && placement.last_column < max_columns
+                && placement.cells().all(|cell| !occupied.contains_key(&cell))
+        };
+
+        let around = placed(circuit);
+        let mut candidates = Vec::new();
+        if let [first, rest @ ..] = around.as_slice() {
+            if !rest.is_empty() && rest.iter().all(|p| p.row == first.row) {
+                let first_column = rest
+                    .iter()
+                    .fold(first.first_column, |column, p| column.min(p.first_column));
+                let last_column = rest
+                    .iter()
+                    .fold(first.last_column, |column, p| column.max(p.last_column));
+                for row in [first.row + 1, first.row - 1] {
+                    candidates.push(Placement {
+                        row,
+                        first_column,
+                        last_column,
+                    });
+                }
+            }
+        }
+        for neighbour in &around {
+            let (row, first, last) = (neighbour.row, neighbour.first_column, neighbour.last_column);
+            // direita, esquerda, embaixo e em cima do vizinho
+            candidates.push(single(row, last + 1));
+            candidates.push(single(row, first - 1));
+            candidates.extend((first..=last).map(|column| single(row + 1, column)));
+            candidates.extend((first..=last).map(|column| single(row - 1, column)));
+        }
+
+        let placement = candidates.into_iter().find(|p| fits(p)).unwrap_or_else(|| {
+            // sem lugar ao lado dos vizinhos: primeira célula livre, que no
+            // pior caso está na linha abaixo da última
+            let bottom = occupied.keys().map(|(row, _)| *row).max().unwrap_or(-1);
+            let (row, column) = (0..=bottom)
+                .flat_map(|row| (0..max_columns).map(move |column| (row, column)))
+                .find(|cell| !occupied.contains_key(cell))
+                .unwrap_or((bottom + 1, 0));
+            single(row, column)
+        });
+
+        for cell in placement.cells() {
+            occupied.insert(cell, circuit);
+        }
+        placements[circuit] = Some(placement);
+    }
+
+    let top = placements
+        .iter()
+        .flatten()
+        .map(|p| p.row)
+        .min()
+        .unwrap_or(0);
+    placements
+        .into_iter()
+        .flatten()
+        .map(|p| Placement {
+            row: p.row - top,
+            ..p
+        })
+        .collect()
+}
+
+/// Vizinhos de cada lado, na ordem em que o contorno passa por eles: cima,
+/// direita, baixo e esquerda no sentido horário; cima, esquerda, baixo e
+/// direita no anti-horário.
+fn touching(placements: &[Placement], circuit: usize, clockwise: bool) -> [Vec<usize>; 4] {
+    let this = placements[circuit];
+    let at = |row: i32, column: i32| {
+        placements
+            .iter()
+            .position(|p| p.row == row && (p.first_column..=p.last_column).contains(&column))
+    };
+
+    let mut sides: [Vec<usize>; 4] = Default::default();
+    let top = (this.first_column..=this.last_column).filter_map(|c| at(this.row - 1, c));
+    let bottom = (this.first_column..=this.last_column)
+        .rev()
+        .filter_map(|c| at(this.row + 1, c));
+    sides[0].extend(top);
+    sides[1].extend(at(this.row, this.last_column + 1));
+    sides[2].extend(bottom);
+    sides[3].extend(at(this.row, this.first_column - 1));
+    for side in &mut sides {
+        side.dedup();
+    }
+    if !clockwise {
+        // espelhado: esquerda e direita trocam e cima e baixo invertem
+        sides.swap(1, 3);
+        sides[0].reverse();
+        sides[2].reverse();
+    }
+    sides
+}
+
+/// Lado e ordem ao longo do lado do vizinho que também usa `track`.
+fn facing(
+    routes: &[Vec<usize>],
+    touching: &[Vec<usize>; 4],
+    track: usize,
+) -> Option<(usize, usize)> {
+    touching.iter().enumerate().find_map(|(side, neighbours)| {
+        neighbours
+            .iter()
+            .position(|&other| routes[other].contains(&track))
+            .map(|along| (side, along))
+    })
+}
+
+/// Lados e sentido do contorno do circuito; o anti-horário só é usado se
+/// deixar menos trilhos fora do lugar.
+fn outline(
+    routes: &[Vec<usize>],
+    placements: &[Placement],
+    circuit: usize,
+) -> ([Vec<usize>; 4], bool) {
+    let arrange = |clockwise: bool| {
+        let touching = touching(placements, circuit, clockwise);
+        let sides = sides(routes, &touching, circuit);
+        let misplaced = misplaced(routes, &touching, &sides);
+        (sides, misplaced)
+    };
+    let (clockwise, clockwise_misplaced) = arrange(true);
+    let (counter, counter_misplaced) = arrange(false);
+    if counter_misplaced < clockwise_misplaced {
+        (counter, false)
+    } else {
+        (clockwise, true)
+    }
+}
+
+/// Divide a rota em quatro lados (cima, direita, baixo, esquerda) na ordem
+/// em que o trem passa pelos trilhos.
+fn split_sides(tracks: &[usize]) -> [Vec<usize>; 4] {
+    let n = tracks.len();
+    [0, 1, 2, 3].map(|side| tracks[side * n / 4..(side + 1) * n / 4].to_vec())
+}
+
+/// Põe cada trilho dividido no lado que encosta no outro circuito e espalha
+/// os demais pelos lados livres, mantendo a ordem da rota.
+fn sides(routes: &[Vec<usize>], touching: &[Vec<usize>; 4], circuit: usize) -> [Vec<usize>; 4] {
+    let route = &routes[circuit];
+    let n = route.len();
+    let fixed: Vec<Option<(usize, usize)>> = route
+        .iter()
+        .map(|&track| facing(routes, touching, track))
+        .collect();
+
+    let first = fixed
+        .iter()
+        .enumerate()
+        .find_map(|(i, fixed)| fixed.map(|(side, _)| (i, side)));
+    let Some((start, first_side)) = first else {
+        return split_sides(route);
+    };
+
+    // percorre a rota a partir do primeiro trilho fixo, guardando a distância
+    // até ele; os lados precisam avançar no sentido do contorno, dando no
+    // máximo uma volta
+    let mut anchors: Vec<(usize, usize)> = Vec::new();
+    let mut last = (0, 0);
+    for offset in 0..n {
+        if let Some((side, along)) = fixed[(start + offset) % n] {
+            let turn = ((side + 4 - first_side) % 4, along);
+            if turn < last {
+                return split_sides(route);
+            }
+            last = turn;
+            anchors.push((offset, side));
+        }
+    }
+
+    let mut sides: [Vec<usize>; 4] = Default::default();
+    for (k, &(offset, side)) in anchors.iter().enumerate() {
+        sides[side].push(route[(start + offset) % n]);
+
+        // o último trilho fixo segue até o primeiro, dando a volta
+        let (end, next_side) = anchors.get(k + 1).copied().unwrap_or((n, first_side));
+        let free: Vec<usize> = (offset + 1..end)
+            .map(|offset| route[(start + offset) % n])
+            .collect();
+
+        // lados estritamente entre este trilho fixo e o próximo
+        let mut gap = (next_side + 4 - side) % 4;
+        if gap == 0 && k + 1 == anchors.len() {
+            gap = 4;
+        }
+        let between: Vec<usize> = (1..gap).map(|step| (side + step) % 4).collect();
+        if between.is_empty() {
+            sides[side].extend(free);
+        } else {
+            let count = free.len();
+            for (j, &free_side) in between.iter().enumerate() {
+                let share = &free[j * count / between.len()..(j + 1) * count / between.len()];
+                sides[free_side].extend_from_slice(share);
+            }
+        }
+    }
+    sides
+}
+
+/// Quantos trilhos ficam fora do lugar: divididos fora da aresta comum ou
+/// esticados sobre um lado vazio, e livres numa aresta com trilhos divididos.
+fn misplaced(routes: &[Vec<usize>], touching: &[Vec<usize>; 4], sides: &[Vec<usize>; 4]) -> usize {
+    let facing_side = |track: usize| facing(routes, touching, track).map(|(side, _)| side);
+    let mut count = 0;
+    for (side, tracks) in sides.iter().enumerate() {
+        let shared_edge = sides
+            .iter()
+            .flatten()
+            .any(|&track| facing_side(track) == Some(side));
+        count += tracks
+            .iter()
+            .filter(|&&track| match facing_side(track) {
+                Some(facing) => facing != side,
+                None => shared_edge,
+            })
+            .count();
+
+        // como em LoopPath::new, o lado vazio fica com o trilho seguinte
+        let next = (1..4).find_map(|step| sides[(side + step) % 4].first());
+        if tracks.is_empty() && next.is_some_and(|&track| facing_side(track).is_some()) {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Um rótulo por trilho: no meio da aresta comum se o trilho é dividido
+/// entre circuitos encostados, senão do lado de dentro do contorno.
+fn labels(
+    layout: &Layout,
+    routes: &[Vec<usize>],
+    loops: &[LoopPath],
+    cell: Vec2,
+) -> Vec<(Vec2, String)> {
+    let inset = 0.15 * cell.min_element();
+    (0..layout.segments.len())
+        .filter_map(|track| {
+            let paths: Vec<&LoopPath> = routes
+                .iter()
+                .zip(loops)
+                .filter(|(route, _)| route.contains(&track))
+                .map(|(_, path)| path)
+                .collect();
+            let anchor = match common_piece(&paths, track) {
+                Some((start, end)) => (start + end) / 2.0,
+                None => {
+                    let path = paths.first()?;
+                    let point = path.point_at(track, 0.5)?;
+                    point + (path.center() - point).normalize_or_zero() * inset
+                }
+            };
+            Some((anchor, layout.segments[track].name.clone()))
+        })
+        .collect()
+}
+
+/// Trecho reto em que os contornos de todos os circuitos que usam o trilho
+/// se sobrepõem; `None` se só um circuito o usa.
+fn common_piece(paths: &[&LoopPath], track: usize) -> Option<(Vec2, Vec2)> {
+    let (first, others) = paths.split_first()?;
+    if others.is_empty() {
+        return None;
+    }
+    first.pieces(track).into_iter().find_map(|piece| {
+        others.iter().try_fold(piece, |piece, other| {
+            other
+                .pieces(track)
+                .into_iter()
+                .find_map(|other| overlap(piece, other))
+        })
+    })
+}
+
+/// Parte comum de dois trechos retos na mesma reta horizontal ou vertical.
+fn overlap(a: (Vec2, Vec2), b: (Vec2, Vec2)) -> Option<(Vec2, Vec2)> {
+    const TOLERANCE: f32 = 0.5;
+    let along = |(start, end): (Vec2, Vec2)| {
+        if (start.y - end.y).abs() < TOLERANCE {
+            Some(Vec2::X)
+        } else if (start.x - end.x).abs() < TOLERANCE {
+            Some(Vec2::Y)
+        } else {
+            None
+        }
+    };
+    let axis = along(a)?;
+    // a componente fora do eixo precisa ser a mesma: mesma reta
+    let across = a.0 - axis * a.0.dot(axis);
+    if along(b)? != axis || across.distance(b.0 - axis * b.0.dot(axis)) > TOLERANCE {
+        return None;
+    }
+
+    let range = |(start, end): (Vec2, Vec2)| {
+        let (start, end) = (start.dot(axis), end.dot(axis));
+        (start.min(end), start.max(end))
+    };
+    let ((a_low, a_high), (b_low, b_high)) = (range(a), range(b));
+    let (low, high) = (a_low.max(b_low), a_high.min(b_high));
+    (high - low > TOLERANCE).then(|| (across + axis * low, across + axis * high))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundled() -> Vec<Layout> {
+        [
+            include_str!("../assets/layouts/two_trains.ron"),
+            include_str!("../assets/layouts/four_trains.ron"),
+            include_str!("../assets/layouts/ten_trains.ron"),
+            include_str!("../assets/layouts/priority_inversion.ron"),
+        ]
+        .into_iter()
+        .map(|source| Layout::from_ron(source).unwrap())
+        .collect()
+    }
+
+    fn layouts() -> Vec<Layout> {
+        let mut layouts = bundled();
+        layouts.extend((1..=12).map(|trains| Layout::chain(trains).unwrap()));
+        layouts
+    }
+
+    fn routes(layout: &Layout) -> Vec<Vec<usize>> {
+        (0..layout.circuits.len())
+            .map(|circuit| layout.route(circuit).concat())
+            .collect()
+    }
+
+    fn draw(layout: &Layout) -> TrackDrawing {
+        compute(layout, Vec2::ZERO, Vec2::new(800.0, 600.0))
+    }
+
+    #[test]
+    fn placements_do_not_overlap() {
+        for layout in layouts() {
+            let placements = place(&routes(&layout));
+            assert_eq!(placements.len(), layout.circuits.len());
+            let mut cells = HashMap::new();
+            for (circuit, placement) in placements.iter().enumerate() {
+                assert!(placement.row >= 0 && placement.first_column >= 0);
+                for cell in placement.cells() {
+                    assert_eq!(cells.insert(cell, circuit), None, "{:?}", placements);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn chained_circuits_touch() {
+        for trains in 2..=12 {
+            let placements = place(&routes(&Layout::chain(trains).unwrap()));
+            for train in 1..trains {
+                let sides = touching(&placements, train, true);
+                assert!(sides.iter().any(|side| side.contains(&(train - 1))));
+            }
+        }
+    }
+
+    #[test]
+    fn split_sides_keeps_route_order() {
+        let route: Vec<usize> = (0..7).collect();
+        let sides = split_sides(&route);
+        assert_eq!(sides.concat(), route);
+        assert!(sides.iter().all(|side| !side.is_empty()));
+        assert_eq!(split_sides(&[4, 2]), [vec![], vec![4], vec![], vec![2]]);
+    }
+
+    #[test]
+    fn sides_keep_the_route_order() {
+        for layout in layouts() {
+            let routes = routes(&layout);
+            let placements = place(&routes);
+            for (circuit, route) in routes.iter().enumerate() {
+                for clockwise in [true, false] {
+                    let touching = touching(&placements, circuit, clockwise);
+                    let tracks = sides(&routes, &touching, circuit).concat();
+                    // a volta pode começar em qualquer trilho da rota
+                    let start = route.iter().position(|track| Some(track) == tracks.first());
+                    let start = start.unwrap();
+                    let rotated = [&route[start..], &route[..start]].concat();
+                    assert_eq!(tracks, rotated, "{}", layout.circuits[circuit].name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn counterclockwise_mirrors_the_sides() {
+        let placements = [single(0, 0), single(0, 1), single(1, 0)];
+        assert_eq!(
+            touching(&placements, 0, true),
+            [vec![], vec![1], vec![2], vec![]]
+        );
+        assert_eq!(
+            touching(&placements, 0, false),
+            [vec![], vec![], vec![2], vec![1]]
+        );
+    }
+
+    #[test]
+    fn shared_tracks_lie_on_a_common_edge() {
+        for layout in layouts() {
+            let routes = routes(&layout);
+            let placements = place(&routes);
+            let drawing = draw(&layout);
+            for (circuit, route) in routes.iter().enumerate() {
+                let neighbours = touching(&placements, circuit, true).concat();
+                for &track in route {
+                    // um trilho de três circuitos só encosta em um dos vizinhos
+                    let mut users = neighbours
+                        .iter()
+                        .filter(|&&other| routes[other].contains(&track))
+                        .peekable();
+                    if users.peek().is_none() {
+                        continue;
+                    }
+                    let on_edge = users.any(|&other| {
+                        let paths = [&drawing.loops[circuit], &drawing.loops[other]];
+                        common_piece(&paths, track).is_some()
+                    });
+                    assert!(
+                        on_edge,
+                        "{}: {} fora da aresta comum",
+                        layout.circuits[circuit].name, layout.segments[track].name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ten_trains_shared_tracks_cover_the_whole_edge() {
+        let layout = Layout::from_ron(include_str!("../assets/layouts/ten_trains.ron")).unwrap();
+        let routes = routes(&layout);
+        let drawing = draw(&layout);
+        for (track, segment) in layout.segments.iter().enumerate() {
+            if !segment.shared {
+                continue;
+            }
+            let paths: Vec<&LoopPath> = routes
+                .iter()
+                .zip(&drawing.loops)
+                .filter(|(route, _)| route.contains(&track))
+                .map(|(_, path)| path)
+                .collect();
+            let (start, end) = common_piece(&paths, track).unwrap();
+            let edge = if start.x == end.x {
+                drawing.cell.y
+            } else {
+                drawing.cell.x
+            };
+            assert!((start.distance(end) - edge).abs() < 1.0, "{}", segment.name);
+        }
+    }
+
+    #[test]
+    fn only_tracks_between_opposite_neighbours_leave_the_edge() {
+        let layout = Layout::from_ron(include_str!("../assets/layouts/ten_trains.ron")).unwrap();
+        let routes = routes(&layout);
+        let placements = place(&routes);
+        for circuit in 0..routes.len() {
+            let (sides, clockwise) = outline(&routes, &placements, circuit);
+            let touching = touching(&placements, circuit, clockwise);
+            let shared: Vec<usize> = routes[circuit]
+                .iter()
+                .filter_map(|&track| facing(&routes, &touching, track))
+                .map(|(side, _)| side)
+                .collect();
+            let opposite = shared.iter().any(|side| shared.contains(&((side + 2) % 4)));
+            let expected = usize::from(opposite);
+            assert_eq!(
+                misplaced(&routes, &touching, &sides),
+                expected,
+                "{}",
+                layout.circuits[circuit].name
+            );
+        }
+    }
+
+    #[test]
+    fn one_label_per_track_on_every_edge_it_is_drawn() {
+        for layout in bundled() {
+            let routes = routes(&layout);
+            let drawing = draw(&layout);
+            assert_eq!(drawing.labels.len(), layout.segments.len());
+            for ((point, name), track) in drawing.labels.iter().zip(0..) {
+                assert_eq!(*name, layout.segments[track].name);
+                let users: Vec<&LoopPath> = routes
+                    .iter()
+                    .zip(&drawing.loops)
+                    .filter(|(route, _)| route.contains(&track))
+                    .map(|(_, path)| path)
+                    .collect();
+                if common_piece(&users, track).is_some() {
+                    for path in users {
+                        assert!(path.distance_to(track, *point).unwrap() < 1.0, "{}", name);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn overlap_needs_the_same_line() {
+        let piece = |a: (f32, f32), b: (f32, f32)| (Vec2::new(a.0, a.1), Vec2::new(b.0, b.1));
+        assert_eq!(
+            overlap(
+                piece((0.0, 0.0), (10.0, 0.0)),
+                piece((15.0, 0.0), (5.0, 0.0))
+            ),
+            Some(piece((5.0, 0.0), (10.0, 0.0)))
+        );
+        assert_eq!(
+            overlap(
+                piece((0.0, 0.0), (10.0, 0.0)),
+                piece((0.0, 5.0), (10.0, 5.0))
+            ),
+            None
+        );
+        assert_eq!(
+            overlap(
+                piece((0.0, 0.0), (10.0, 0.0)),
+                piece((10.0, 0.0), (10.0, 5.0))
+            ),
+            None
+        );
+        assert_eq!(
+            overlap(
+                piece((0.0, 0.0), (10.0, 0.0)),
+                piece((10.0, 0.0), (20.0, 0.0))
+            ),
+            None
+        );
+    }
+}
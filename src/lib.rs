@@ -1,42 +1,106 @@
 pub mod circuit;
+pub mod clock;
+pub mod control;
+pub mod error;
+pub mod event;
+pub mod lock_tracker;
+pub mod pool_simulation;
+pub mod scenario;
+pub mod simulation;
+pub mod simulation_core;
+pub mod stats;
+pub mod stepper;
 pub mod track;
+pub mod train_machine;
+pub mod virtual_simulation;
 
-
+pub use crate::clock::{Clock, MAX_TIME_SCALE, MIN_TIME_SCALE};
+pub use crate::control::{Command, Stopped, TrainControl};
+pub use crate::error::{Error, LockRecover};
+pub use crate::event::{Event, EventKind, EventLog};
+pub use crate::lock_tracker::{Deadlock, LockTracker};
+pub use crate::pool_simulation::PoolSimulation;
+pub use crate::scenario::{Scenario, ScenarioError, TrainScenario, TrainSetup};
+pub use crate::simulation::{Executor, Simulation};
+pub use crate::simulation_core::SimulationCore;
+pub use crate::stats::Statistics;
+pub use crate::stepper::{Blocked, Step};
 pub use crate::track::Track;
+pub use crate::train_machine::{Network, TrainMachine};
+pub use crate::virtual_simulation::VirtualSimulation;
 
-// pub use crate::circuit::green_circuit;
-// pub use crate::circuit::purple_circuit;
-
-
+/// Limites de velocidade de um trem quando a malha não define outros.
+pub const MIN_VELOCITY: f64 = 1.0;
+pub const MAX_VELOCITY: f64 = 6.0;
 
-pub enum Color {
-    GREEN,
-    PURPLE,
-    RED,
-    BLUE,
-}
+/// Quanto [`Train::increment`] e [`Train::decrement`] mudam a velocidade.
+pub const VELOCITY_STEP: f64 = 1.0;
 
 #[derive(Clone)]
 pub struct Train {
     pub id: u64,
-    pub velocity: u64,
+    pub velocity: f64,
+    pub min_velocity: f64,
+    pub max_velocity: f64,
 }
 
 impl Train {
-    pub fn new(id: u64, velocity: u64) -> Self {
-        Train { velocity, id }
+    pub fn new(id: u64, velocity: f64) -> Result<Self, Error> {
+        Train::with_limits(id, velocity, MIN_VELOCITY, MAX_VELOCITY)
     }
 
-    pub fn increment(&mut self) {
-        if self.velocity < 6 {
-            self.velocity += 1;
+    /// Falha se os limites não formam um intervalo `0 <= min <= max` ou se
+    /// `velocity` está fora deles.
+    pub fn with_limits(
+        id: u64,
+        velocity: f64,
+        min_velocity: f64,
+        max_velocity: f64,
+    ) -> Result<Self, Error> {
+        if !(0.0 <= min_velocity && min_velocity <= max_velocity && max_velocity.is_finite()) {
+            return Err(Error::InvalidVelocityLimits {
+                train: id,
+                min: min_velocity,
+                max: max_velocity,
+            });
         }
+        let mut train = Train {
+            id,
+            velocity: min_velocity,
+            min_velocity,
+            max_velocity,
+        };
+        train.set_velocity(velocity)?;
+        Ok(train)
     }
 
-    pub fn decrement(&mut self) {
-        if self.velocity > 1 {
-            self.velocity -= 1;
+    /// Muda a velocidade; fora dos limites do trem nada muda e o erro diz quais são.
+    pub fn set_velocity(&mut self, velocity: f64) -> Result<(), Error> {
+        if !(self.min_velocity..=self.max_velocity).contains(&velocity) {
+            return Err(Error::InvalidVelocity {
+                train: self.id,
+                velocity,
+                min: self.min_velocity,
+                max: self.max_velocity,
+            });
         }
+        self.velocity = velocity;
+        Ok(())
     }
-}
 
+    /// Sobe [`VELOCITY_STEP`], parando no máximo; já no máximo é um erro.
+    pub fn increment(&mut self) -> Result<(), Error> {
+        if self.velocity >= self.max_velocity {
+            return self.set_velocity(self.velocity + VELOCITY_STEP);
+        }
+        self.set_velocity((self.velocity + VELOCITY_STEP).min(self.max_velocity))
+    }
+
+    /// Desce [`VELOCITY_STEP`], parando no mínimo; já no mínimo é um erro.
+    pub fn decrement(&mut self) -> Result<(), Error> {
+        if self.velocity <= self.min_velocity {
+            return self.set_velocity(self.velocity - VELOCITY_STEP);
+        }
+        self.set_velocity((self.velocity - VELOCITY_STEP).max(self.min_velocity))
+    }
+}
@@ -0,0 +1,194 @@
+use crate::LockRecover;
+use std::{collections::HashMap, fmt, sync::Mutex};
+
+/// Ciclo no grafo de espera: `trains[i]` espera por `segments[i]`, que está
+/// com `trains[i + 1]` (o último espera pelo trilho do primeiro).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deadlock {
+    pub trains: Vec<u64>,
+    pub segments: Vec<String>,
+}
+
+impl fmt::Display for Deadlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deadlock")?;
+        for (i, (train, segment)) in self.trains.iter().zip(&self.segments).enumerate() {
+            let holder = self.trains[(i + 1) % self.trains.len()];
+            let separator = if i == 0 { ":" } else { ";" };
+            write!(
+                f,
+                "{} train {} waits for {} held by train {}",
+                separator, train, segment, holder
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct WaitForGraph {
+    holders: HashMap<usize, u64>,
+    waiting: HashMap<u64, usize>,
+    /// Trens esperando, na ordem em que começaram a esperar.
+    queue: Vec<u64>,
+}
+
+impl WaitForGraph {
+    fn cycle_from(&self, train: u64) -> Option<Vec<(u64, usize)>> {
+        let mut cycle = Vec::new();
+        let mut current = train;
+
+        loop {
+            let segment = *self.waiting.get(&current)?;
+            let holder = *self.holders.get(&segment)?;
+            cycle.push((current, segment));
+
+            if holder == train {
+                return Some(cycle);
+            }
+            if cycle.iter().any(|(visited, _)| *visited == holder) {
+                // existe um ciclo, mas `train` só está esperando por ele
+                return None;
+            }
+            current = holder;
+        }
+    }
+}
+
+/// Registra quem segura e quem espera por cada trilho para detectar deadlocks.
+pub struct LockTracker {
+    segments: Vec<String>,
+    graph: Mutex<WaitForGraph>,
+}
+
+impl LockTracker {
+    pub fn new(segments: Vec<String>) -> LockTracker {
+        LockTracker {
+            segments,
+            graph: Mutex::new(WaitForGraph::default()),
+        }
+    }
+
+    /// Chamado antes de bloquear no lock do trilho; o deadlock que a espera
+    /// fechar aparece em [`LockTracker::deadlocks`].
+    pub fn waiting(&self, train: u64, segment: usize) {
+        let mut graph = self.graph.lock_recover();
+        graph.waiting.insert(train, segment);
+        graph.queue.retain(|waiting| *waiting != train);
+        graph.queue.push(train);
+    }
+
+    pub fn acquired(&self, train: u64, segment: usize) {
+        let mut graph = self.graph.lock_recover();
+        graph.waiting.remove(&train);
+        graph.queue.retain(|waiting| *waiting != train);
+        graph.holders.insert(segment, train);
+    }
+
+    pub fn released(&self, train: u64, segment: usize) {
+        let mut graph = self.graph.lock_recover();
+        if graph.holders.get(&segment) == Some(&train) {
+            graph.holders.remove(&segment);
+        }
+    }
+
+    /// Remove o trem do grafo de espera, por exemplo quando ele é parado.
+    pub fn forget(&self, train: u64) {
+        let mut graph = self.graph.lock_recover();
+        graph.waiting.remove(&train);
+        graph.queue.retain(|waiting| *waiting != train);
+    }
+
+    pub fn holder(&self, segment: usize) -> Option<u64> {
+        self.graph.lock_recover().holders.get(&segment).cloned()
+    }
+
+    /// Trens esperando pelo trilho, do que espera há mais tempo ao mais novo.
+    pub fn waiting_for(&self, segment: usize) -> Vec<u64> {
+        let graph = self.graph.lock_recover();
+        graph
+            .queue
+            .iter()
+            .filter(|train| graph.waiting.get(train) == Some(&segment))
+            .cloned()
+            .collect()
+    }
+
+    /// Todos os deadlocks presentes agora no grafo de espera.
+    pub fn deadlocks(&self) -> Vec<Deadlock> {
+        let graph = self.graph.lock_recover();
+        let mut trains: Vec<u64> = graph.waiting.keys().cloned().collect();
+        trains.sort_unstable();
+
+        let mut deadlocks: Vec<Deadlock> = Vec::new();
+        for train in trains {
+            if deadlocks
+                .iter()
+                .any(|deadlock| deadlock.trains.contains(&train))
+            {
+                continue;
+            }
+            if let Some(cycle) = graph.cycle_from(train) {
+                deadlocks.push(self.report(cycle));
+            }
+        }
+        deadlocks
+    }
+
+    fn report(&self, cycle: Vec<(u64, usize)>) -> Deadlock {
+        let (trains, segments) = cycle
+            .into_iter()
+            .map(|(train, segment)| (train, self.segments[segment].clone()))
+            .unzip();
+        Deadlock { trains, segments }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> LockTracker {
+        LockTracker::new(vec!["L1".to_string(), "L2".to_string(), "L3".to_string()])
+    }
+
+    #[test]
+    fn two_trains_waiting_for_each_other_deadlock() {
+        let tracker = tracker();
+        tracker.acquired(0, 0);
+        tracker.acquired(1, 1);
+        tracker.waiting(0, 1);
+        assert!(tracker.deadlocks().is_empty());
+
+        tracker.waiting(1, 0);
+        let deadlock = Deadlock {
+            trains: vec![0, 1],
+            segments: vec!["L2".to_string(), "L1".to_string()],
+        };
+        assert_eq!(tracker.deadlocks(), vec![deadlock.clone()]);
+        assert_eq!(
+            deadlock.to_string(),
+            "deadlock: train 0 waits for L2 held by train 1; train 1 waits for L1 held by train 0"
+        );
+
+        tracker.released(1, 1);
+        tracker.acquired(0, 1);
+        assert!(tracker.deadlocks().is_empty());
+    }
+
+    #[test]
+    fn a_train_behind_a_cycle_is_not_part_of_it() {
+        let tracker = tracker();
+        tracker.acquired(0, 0);
+        tracker.acquired(1, 1);
+        tracker.waiting(0, 1);
+        tracker.waiting(1, 0);
+        tracker.waiting(2, 0);
+
+        assert_eq!(tracker.graph.lock_recover().cycle_from(2), None);
+        let deadlocks = tracker.deadlocks();
+        assert_eq!(deadlocks.len(), 1);
+        assert_eq!(deadlocks[0].trains, vec![0, 1]);
+        assert_eq!(tracker.waiting_for(0), vec![1, 2]);
+    }
+}
@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+
+/// Contorno retangular de um circuito, percorrido no sentido horário a
+/// partir do canto superior esquerdo ou, espelhado, no anti-horário a partir
+/// do canto superior direito.
+pub struct LoopPath {
+    center: Vec2,
+    size: Vec2,
+    clockwise: bool,
+    /// trecho do perímetro (início, fim) ocupado por cada trilho do circuito
+    segments: Vec<(usize, f32, f32)>,
+}
+
+impl LoopPath {
+    /// `sides` lista os trilhos de cada lado (cima, direita, baixo, esquerda)
+    /// na ordem em que o trem passa por eles; cada lado é dividido igualmente
+    /// e um lado vazio fica com o trilho seguinte. Sem `clockwise` os lados
+    /// são cima, esquerda, baixo e direita.
+    pub fn new(center: Vec2, size: Vec2, sides: [Vec<usize>; 4], clockwise: bool) -> LoopPath {
+        let lengths = [size.x, size.y, size.x, size.y];
+        let mut segments: Vec<(usize, f32, f32)> = Vec::new();
+        let mut start = 0.0;
+        // comprimento dos lados vazios ainda sem trilho
+        let mut pending = 0.0;
+
+        for (side, length) in sides.iter().zip(lengths) {
+            if side.is_empty() {
+                pending += length;
+            }
+
+            let step = length / side.len().max(1) as f32;
+            for (i, track) in side.iter().enumerate() {
+                let begin = start + i as f32 * step;
+                segments.push((*track, begin - pending, begin + step));
+                pending = 0.0;
+            }
+            start += length;
+        }
+        // lados vazios no fim dão a volta até o primeiro trilho
+        if let Some((_, begin, _)) = segments.first_mut() {
+            *begin -= pending;
+        }
+
+        LoopPath {
+            center,
+            size,
+            clockwise,
+            segments,
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.center
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    /// Ponto do contorno em que o trem está ao percorrer `progress` do trilho.
+    pub fn point_at(&self, track: usize, progress: f32) -> Option<Vec2> {
+        let (_, begin, end) = self.segments.iter().find(|(t, _, _)| *t == track)?;
+        Some(self.perimeter_point(begin + progress.clamp(0.0, 1.0) * (end - begin)))
+    }
+
+    /// Trechos retos (início, fim) do contorno ocupados pelo trilho; um trilho
+    /// que dobra uma quina tem mais de um.
+    pub fn pieces(&self, track: usize) -> Vec<(Vec2, Vec2)> {
+        let Some((_, begin, end)) = self.segments.iter().find(|(t, _, _)| *t == track) else {
+            return Vec::new();
+        };
+
+        let perimeter = 2.0 * (self.size.x + self.size.y);
+        let corners = [
+            0.0,
+            self.size.x,
+            self.size.x + self.size.y,
+            2.0 * self.size.x + self.size.y,
+        ];
+        let mut cuts: Vec<f32> = [-perimeter, 0.0, perimeter]
+            .iter()
+            .flat_map(|lap| corners.map(|corner| corner + lap))
+            .filter(|cut| cut > begin && cut < end)
+            .collect();
+        cuts.sort_by(f32::total_cmp);
+
+        let mut points = vec![*begin];
+        points.extend(cuts);
+        points.push(*end);
+        points
+            .windows(2)
+            .map(|pair| (self.perimeter_point(pair[0]), self.perimeter_point(pair[1])))
+            .collect()
+    }
+
+    /// Menor distância de `point` até o trecho do contorno ocupado pelo trilho.
+    pub fn distance_to(&self, track: usize, point: Vec2) -> Option<f32> {
+        const SAMPLES: usize = 32;
+        let (_, begin, end) = self.segments.iter().find(|(t, _, _)| *t == track)?;
+        (0..=SAMPLES)
+            .map(|i| begin + (end - begin) * i as f32 / SAMPLES as f32)
+            .map(|distance| self.perimeter_point(distance).distance(point))
+            .reduce(f32::min)
+    }
+
+    fn perimeter_point(&self, distance: f32) -> Vec2 {
+        let half = self.size / 2.0;
+        let (left, right) = (self.center.x - half.x, self.center.x + half.x);
+        let (bottom, top) = (self.center.y - half.y, self.center.y + half.y);
+
+        let distance = distance.rem_euclid(2.0 * (self.size.x + self.size.y));
+        let point = if distance < self.size.x {
+            Vec2::new(left + distance, top)
+        } else if distance < self.size.x + self.size.y {
+            Vec2::new(right, top - (distance - self.size.x))
+        } else if distance < 2.0 * self.size.x + self.size.y {
+            Vec2::new(right - (distance - self.size.x - self.size.y), bottom)
+        } else {
+            Vec2::new(left, bottom + (distance - 2.0 * self.size.x - self.size.y))
+        };
+        if self.clockwise {
+            point
+        } else {
+            Vec2::new(2.0 * self.center.x - point.x, point.y)
+        }
+    }
+}
@@ -1,36 +1,53 @@
 use bevy::{prelude::*, window::PresentMode};
 use buttons_ui_plugin::ButtonUiPlugin;
+use cli::Options;
+use ecs_plugin::{EcsSimulationPlugin, StepMode};
+use inspector_plugin::InspectorPlugin;
+use keyboard_plugin::KeyboardPlugin;
+use scenario_plugin::{ScenarioList, ScenarioPlugin};
+use segment_plugin::SegmentPlugin;
 use setup_plugin::SetupPlugin;
-use std::sync::{Arc, Mutex};
+use stats_plugin::StatsPlugin;
+use std::time::Duration;
 use text_plugin::TextPlugin;
 
-use tracks::track::TrackState;
+use tracks::{
+    circuit::{GrantOrder, GrantPolicy, Layout, SyncKind},
+    Deadlock, EventLog, Executor, PoolSimulation, Scenario, Simulation, Statistics, Train,
+    TrainSetup, VirtualSimulation,
+};
 use train_plugin::TrainPlugin;
 
 pub mod buttons_ui_plugin;
+pub mod cli;
+pub mod ecs_plugin;
+pub mod inspector_plugin;
+pub mod keyboard_plugin;
+pub mod layout_engine;
+pub mod loop_path;
+pub mod scenario_plugin;
+pub mod segment_plugin;
 pub mod setup_plugin;
+pub mod stats_plugin;
 pub mod text_plugin;
 pub mod train_plugin;
 
+/// Cor do trem de cada circuito, na ordem da malha.
 pub struct TrainMaterials {
-    green_train_material: Handle<ColorMaterial>,
-    purple_train_material: Handle<ColorMaterial>,
-    red_train_material: Handle<ColorMaterial>,
-    blue_train_material: Handle<ColorMaterial>,
-    train_block_size: f32,
+    materials: Vec<Handle<ColorMaterial>>,
 }
 
 pub struct TrackMaterials {
-    green_track_material: Handle<ColorMaterial>,
-    purple_track_material: Handle<ColorMaterial>,
-    red_track_material: Handle<ColorMaterial>,
-    blue_track_material: Handle<ColorMaterial>,
+    materials: Vec<Handle<ColorMaterial>>,
     background_material: Handle<ColorMaterial>,
 }
 
 pub struct UiTrackPos {
     track_pos: Vec<(Vec3, String)>,
-    center_blue_track: Vec2,
+    label_size: f32,
+    title_pos: Vec2,
+    train_size: f32,
+    loops: Vec<loop_path::LoopPath>,
 }
 
 pub struct HalfWindowSize {
@@ -41,36 +58,236 @@ pub struct HalfWindowSize {
 pub enum ButtonAction {
     INCREMENT,
     DECREMENT,
+    PAUSE,
 }
 
-#[derive(Clone,Component)]
-pub enum TrainID {
-    GREEN,
-    PURPLE,
-    RED,
-    BLUE,
+/// Botões que agem sobre a simulação inteira.
+#[derive(Component)]
+pub enum ControlAction {
+    PAUSE,
+    RESTART,
+    SLOWER,
+    FASTER,
 }
+
+/// Índice do trem, que é também o do seu circuito na malha.
+#[derive(Clone, Copy, Component)]
+pub struct TrainID(usize);
+
+/// Linha de botões de um trem no painel.
+#[derive(Component)]
+pub struct TrainRow(usize);
+
+#[derive(Component)]
+pub struct StatsText;
+
 #[derive(Component)]
 pub struct TrainState {
-    state: Arc<Mutex<TrackState>>,
+    circuit: usize,
 }
 
+/// Largura do painel de botões, em porcentagem da janela.
+const PANEL_WIDTH: f32 = 35.0;
+
 const APP_NAME: &str = "Visualização da dinâmica dos trens";
+const LAYOUT_PATH: &str = "assets/layouts/four_trains.ron";
+const SCENARIOS_DIR: &str = "assets/scenarios";
+const SYNC_STRATEGY_VAR: &str = "SYNC_STRATEGY";
+const INITIAL_VELOCITIES: [f64; 4] = [2.0, 3.0, 3.0, 4.0];
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|error| fail(error));
+    if options.help {
+        print!("{}", cli::HELP);
+        return;
+    }
+
+    let scenario = options.scenario.as_ref().map(|path| {
+        Scenario::load(path).unwrap_or_else(|error| fail(format!("{}: {}", path, error)))
+    });
+
+    // sem `--layout`, vale a malha para a qual o cenário foi escrito
+    let layout_path = options
+        .layout
+        .as_deref()
+        .or_else(|| scenario.as_ref()?.layout.as_deref())
+        .unwrap_or(LAYOUT_PATH);
+    let layout = match options.chain {
+        Some(trains) => Layout::chain(trains).unwrap_or_else(|error| fail(error)),
+        None => Layout::load(layout_path)
+            .unwrap_or_else(|error| fail(format!("{}: {}", layout_path, error))),
+    };
+
+    let sync: SyncKind = match (options.sync, std::env::var(SYNC_STRATEGY_VAR)) {
+        (Some(sync), _) => sync,
+        (None, Ok(name)) => name.parse().unwrap_or_else(|error| fail(error)),
+        (None, Err(_)) => SyncKind::default(),
+    };
+
+    let velocities = match options.speeds.clone() {
+        Some(speeds) if speeds.len() > layout.circuits.len() => fail(format!(
+            "--speeds gives {} speeds but the layout has {} trains",
+            speeds.len(),
+            layout.circuits.len()
+        )),
+        Some(speeds) => speeds,
+        // trens além dos padrões ficam com a velocidade mínima
+        None => INITIAL_VELOCITIES
+            .iter()
+            .copied()
+            .take(layout.circuits.len())
+            .collect(),
+    };
+
+    let setups = match &scenario {
+        Some(scenario) => scenario
+            .setups(&layout, &velocities)
+            .unwrap_or_else(|error| fail(format!("{}: {}", scenario.name, error))),
+        None => TrainSetup::defaults(&layout, &velocities),
+    };
+    // `defaults` traz as velocidades para os limites; as pedidas na linha de
+    // comando precisam já estar dentro deles, como as dos cenários
+    for (i, (setup, speed)) in setups
+        .iter()
+        .zip(options.speeds.iter().flatten())
+        .enumerate()
+    {
+        Train::with_limits(i as u64, *speed, setup.min_velocity, setup.max_velocity)
+            .unwrap_or_else(|error| fail(format!("--speeds: {}", error)));
+    }
+    let scenarios = ScenarioList::load(SCENARIOS_DIR, &layout, &velocities, scenario.as_ref())
+        .unwrap_or_else(|error| fail(error));
+    let mut simulation =
+        Simulation::with_setups(layout, sync, setups).unwrap_or_else(|error| fail(error));
+    println!("sync strategy: {}", simulation.interlocking.strategy_name());
+    simulation.set_grant_policy(GrantPolicy {
+        order: options.grant,
+        inheritance: options.inherit,
+    });
+    if options.grant != GrantOrder::Arbitrary {
+        let inheritance = if options.inherit {
+            " with inheritance"
+        } else {
+            ""
+        };
+        println!("grant order: {}{}", options.grant, inheritance);
+    }
+    if let Some(scale) = options.time_scale {
+        simulation
+            .set_time_scale(scale)
+            .unwrap_or_else(|error| fail(error));
+    }
+
+    if options.headless {
+        let duration = options.duration;
+        let virtual_events;
+        let pool;
+        let events = if options.virtual_clock {
+            virtual_events = run_virtual(&simulation, sync, options.seed, duration);
+            &virtual_events
+        } else if let Some(workers) = options.workers {
+            pool = run_pool(&simulation, sync, workers, duration);
+            &*pool.events
+        } else {
+            run_headless(&simulation, duration);
+            &simulation.events
+        };
+
+        if let Some(path) = &options.trace {
+            events
+                .export(path)
+                .unwrap_or_else(|error| fail(format!("{}: {}", path, error)));
+        }
+
+        if options.stats {
+            let statistics = Statistics::from_events(&simulation.layout, &events.events());
+            println!("{}", statistics.report(duration));
+        }
+        return;
+    }
+
+    if options.ecs {
+        simulation.set_executor(Executor::Stepped);
+    }
+
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
         .insert_resource(WindowDescriptor {
-            title: APP_NAME.to_string(),
-            width: 600.0,
-            height: 800.0,
-            present_mode:PresentMode::Fifo,
+            title: options.title.unwrap_or_else(|| APP_NAME.to_string()),
+            width: options.width,
+            height: options.height,
+            present_mode: PresentMode::Fifo,
             ..Default::default()
         })
+        .insert_resource(simulation)
+        .insert_resource(scenarios)
         .add_plugin(SetupPlugin)
         .add_plugin(TrainPlugin)
         .add_plugin(TextPlugin)
         .add_plugin(ButtonUiPlugin)
-      
-        .run();
+        .add_plugin(StatsPlugin)
+        .add_plugin(InspectorPlugin)
+        .add_plugin(SegmentPlugin)
+        .add_plugin(KeyboardPlugin)
+        .add_plugin(ScenarioPlugin);
+    if options.ecs {
+        app.insert_resource(StepMode::new(options.step))
+            .add_plugin(EcsSimulationPlugin);
+    }
+    app.run();
+}
+
+/// Erros de uso e de arquivos encerram o programa com uma mensagem, sem pânico.
+fn fail<E: std::fmt::Display>(error: E) -> ! {
+    eprintln!("tracks: {}", error);
+    eprintln!("use --help para ver as opções");
+    std::process::exit(2);
+}
+
+fn run_headless(simulation: &Simulation, duration: Duration) {
+    let deadlocks = simulation.run_for(duration, |time, train, track| {
+        let circuit = &simulation.layout.circuits[train].name;
+        println!("{:>9.3}s {} -> {}", time.as_secs_f64(), circuit, track.name);
+    });
+    report_deadlocks(&deadlocks);
+}
+
+fn run_pool(
+    threaded: &Simulation,
+    sync: SyncKind,
+    workers: usize,
+    duration: Duration,
+) -> PoolSimulation {
+    let layout = threaded.layout.clone();
+    let simulation = PoolSimulation::new(layout, sync, threaded.setups().to_vec(), workers)
+        .unwrap_or_else(|error| fail(error));
+    simulation
+        .set_time_scale(threaded.time_scale())
+        .unwrap_or_else(|error| fail(error));
+    simulation.set_grant_policy(threaded.grant_policy());
+    let deadlocks = simulation.run_for(duration, |time, train, track| {
+        let circuit = &simulation.layout.circuits[train].name;
+        println!("{:>9.3}s {} -> {}", time.as_secs_f64(), circuit, track.name);
+    });
+    report_deadlocks(&deadlocks);
+    simulation
+}
+
+/// Os deadlocks vão para a saída de erro, separados da lista de trilhos.
+fn report_deadlocks(deadlocks: &[Deadlock]) {
+    for deadlock in deadlocks {
+        eprintln!("{}", deadlock);
+    }
+}
+
+fn run_virtual(threaded: &Simulation, sync: SyncKind, seed: u64, duration: Duration) -> EventLog {
+    let layout = &threaded.layout;
+    let mut simulation = VirtualSimulation::with_setups(layout, sync, threaded.setups(), seed)
+        .unwrap_or_else(|error| fail(error));
+    simulation.set_grant_policy(threaded.grant_policy());
+    simulation.run_until(duration, |time, train, track| {
+        let circuit = &layout.circuits[train].name;
+        println!("{:>9.3}s {} -> {}", time.as_secs_f64(), circuit, track.name);
+    });
+    simulation.events
 }
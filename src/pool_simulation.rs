@@ -0,0 +1,156 @@
+use crate::{
+    circuit::{Layout, SyncKind},
+    simulation_core::SimulationCore,
+    track::TICK,
+    train_machine::TrainMachine,
+    Deadlock, Error, LockRecover, Track, TrainSetup,
+};
+use std::{
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Máquina de um trem e o instante em que ela foi avançada pela última vez.
+struct Task {
+    machine: TrainMachine,
+    last: Option<Duration>,
+}
+
+/// Tudo o que as tarefas dividem entre as threads do pool.
+struct Shared {
+    core: SimulationCore,
+    tasks: Vec<Mutex<Task>>,
+    running: AtomicBool,
+}
+
+impl Shared {
+    /// `now` é o tempo simulado do relógio de [`crate::EventLog::clock`].
+    fn advance(&self, task: &Mutex<Task>, now: Duration) {
+        let mut task = task.lock_recover();
+        let elapsed = task
+            .last
+            .map_or(0.0, |last| now.saturating_sub(last).as_secs_f64());
+        task.last = Some(now);
+        if !self.core.is_paused(task.machine.train()) {
+            task.machine.advance(elapsed, &self.core.network());
+        }
+    }
+}
+
+/// Os mesmos trilhos, rotas e estratégias da [`crate::Simulation`], mas cada
+/// trem é uma [`TrainMachine`] em vez de uma thread: um pool fixo de
+/// threads (ou quem chamar [`PoolSimulation::step`]) avança todos os trens,
+/// o que permite simular centenas deles.
+pub struct PoolSimulation {
+    shared: Arc<Shared>,
+    workers: usize,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl PoolSimulation {
+    /// `workers` threads dividem os trens; com 0 nenhuma thread é criada e
+    /// os trens só andam em [`PoolSimulation::step`].
+    pub fn new(
+        layout: Layout,
+        sync: SyncKind,
+        setups: Vec<TrainSetup>,
+        workers: usize,
+    ) -> Result<PoolSimulation, Error> {
+        let core = SimulationCore::new(layout, sync, setups)?;
+        let tasks = TrainMachine::from_setups(&core.layout, core.setups())
+            .into_iter()
+            .map(|machine| {
+                Mutex::new(Task {
+                    machine,
+                    last: None,
+                })
+            })
+            .collect();
+
+        Ok(PoolSimulation {
+            shared: Arc::new(Shared {
+                core,
+                tasks,
+                running: AtomicBool::new(false),
+            }),
+            workers,
+            threads: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Avança todos os trens até agora, sem threads; serve para quem já tem
+    /// o próprio laço, como um sistema do Bevy.
+    pub fn step(&self) {
+        let now = self.events.clock().now();
+        for task in &self.shared.tasks {
+            self.shared.advance(task, now);
+        }
+    }
+
+    /// Coloca o pool para rodar; a thread `k` cuida das máquinas `k`, `k + workers`...
+    /// Se ele já está rodando, não faz nada.
+    pub fn spawn(&self) {
+        let mut threads = self.threads.lock_recover();
+        if !threads.is_empty() {
+            return;
+        }
+        self.shared.running.store(true, Ordering::Relaxed);
+        for worker in 0..self.workers {
+            let shared = self.shared.clone();
+            let workers = self.workers;
+            threads.push(thread::spawn(move || {
+                while shared.running.load(Ordering::Relaxed) {
+                    let now = shared.core.events.clock().now();
+                    for task in shared.tasks.iter().skip(worker).step_by(workers) {
+                        shared.advance(task, now);
+                    }
+                    thread::sleep(TICK);
+                }
+            }));
+        }
+    }
+
+    /// Para o pool e libera os trilhos; cada trem recomeça o passo em que estava.
+    pub fn stop(&self) {
+        self.shared.running.store(false, Ordering::Relaxed);
+        for thread in self.threads.lock_recover().drain(..) {
+            let _ = thread.join();
+        }
+        let network = self.shared.core.network();
+        for task in &self.shared.tasks {
+            let mut task = task.lock_recover();
+            task.machine.reset(&network);
+            task.last = None;
+        }
+    }
+
+    /// Como [`crate::Simulation::run_for`], com os trens avançados pelo pool.
+    pub fn run_for<F>(&self, duration: Duration, on_enter: F) -> Vec<Deadlock>
+    where
+        F: FnMut(Duration, usize, &Track),
+    {
+        self.spawn();
+        let deadlocks = self.shared.core.watch(duration, on_enter);
+        self.stop();
+        deadlocks
+    }
+}
+
+impl Deref for PoolSimulation {
+    type Target = SimulationCore;
+
+    fn deref(&self) -> &SimulationCore {
+        &self.shared.core
+    }
+}
+
+impl Drop for PoolSimulation {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{circuit::Layout, Error, Train};
+use serde::Deserialize;
+use std::{fmt, fs, path::Path};
+
+/// Estado inicial de um trem em um cenário; o que faltar vem da malha.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TrainScenario {
+    pub circuit: String,
+    /// Trilho em que o trem começa; precisa abrir um passo da rota.
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default)]
+    pub velocity: Option<f64>,
+    #[serde(default)]
+    pub min_velocity: Option<f64>,
+    #[serde(default)]
+    pub max_velocity: Option<f64>,
+    #[serde(default)]
+    pub priority: Option<u32>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Situação inicial nomeada, para reproduzir uma disputa específica.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    /// Malha para a qual o cenário foi escrito.
+    #[serde(default)]
+    pub layout: Option<String>,
+    #[serde(default)]
+    pub trains: Vec<TrainScenario>,
+}
+
+/// Configuração de um trem já conferida contra a malha.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainSetup {
+    /// Passo da rota por onde o trem começa.
+    pub start_step: usize,
+    pub velocity: f64,
+    pub min_velocity: f64,
+    pub max_velocity: f64,
+    pub priority: u32,
+    pub enabled: bool,
+}
+
+impl TrainSetup {
+    /// Todos os trens ligados, no começo da rota, com a velocidade
+    /// `velocities[i]` (a mínima se não houver) trazida para os limites da malha.
+    pub fn defaults(layout: &Layout, velocities: &[f64]) -> Vec<TrainSetup> {
+        layout
+            .circuits
+            .iter()
+            .enumerate()
+            .map(|(i, circuit)| TrainSetup {
+                start_step: 0,
+                velocity: velocities
+                    .get(i)
+                    .cloned()
+                    .unwrap_or(circuit.min_velocity)
+                    .max(circuit.min_velocity)
+                    .min(circuit.max_velocity),
+                min_velocity: circuit.min_velocity,
+                max_velocity: circuit.max_velocity,
+                priority: circuit.priority,
+                enabled: true,
+            })
+            .collect()
+    }
+
+    pub fn train(&self, id: u64) -> Result<Train, Error> {
+        Train::with_limits(id, self.velocity, self.min_velocity, self.max_velocity)
+    }
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(std::io::Error),
+    Parse(ron::Error),
+    UnknownCircuit(String),
+    DuplicateCircuit(String),
+    InvalidStart { circuit: String, segment: String },
+    InvalidVelocityLimits(String),
+    InvalidVelocity(String),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(error) => write!(f, "could not read scenario: {}", error),
+            ScenarioError::Parse(error) => write!(f, "invalid scenario: {}", error),
+            ScenarioError::UnknownCircuit(circuit) => {
+                write!(f, "scenario uses unknown circuit {}", circuit)
+            }
+            ScenarioError::DuplicateCircuit(circuit) => {
+                write!(f, "circuit {} configured twice", circuit)
+            }
+            ScenarioError::InvalidStart { circuit, segment } => write!(
+                f,
+                "circuit {} cannot start at {}: it must be the first segment of a route step",
+                circuit, segment
+            ),
+            ScenarioError::InvalidVelocityLimits(circuit) => write!(
+                f,
+                "circuit {} needs 0 <= min_velocity <= max_velocity",
+                circuit
+            ),
+            ScenarioError::InvalidVelocity(circuit) => write!(
+                f,
+                "circuit {} needs min_velocity <= velocity <= max_velocity",
+                circuit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl From<std::io::Error> for ScenarioError {
+    fn from(error: std::io::Error) -> Self {
+        ScenarioError::Io(error)
+    }
+}
+
+impl From<ron::Error> for ScenarioError {
+    fn from(error: ron::Error) -> Self {
+        ScenarioError::Parse(error)
+    }
+}
+
+impl Scenario {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Scenario, ScenarioError> {
+        Scenario::from_ron(&fs::read_to_string(path)?)
+    }
+
+    /// Só confere a sintaxe; a malha é conferida em [`Scenario::setups`].
+    pub fn from_ron(source: &str) -> Result<Scenario, ScenarioError> {
+        Ok(ron::from_str(source)?)
+    }
+
+    /// Configuração de cada trem da malha: a de [`TrainSetup::defaults`]
+    /// com o que o cenário muda por cima.
+    pub fn setups(
+        &self,
+        layout: &Layout,
+        velocities: &[f64],
+    ) -> Result<Vec<TrainSetup>, ScenarioError> {
+        let mut setups = TrainSetup::defaults(layout, velocities);
+        let mut configured = vec![false; setups.len()];
+
+        for train in &self.trains {
+            let circuit = layout
+                .circuits
+                .iter()
+                .position(|circuit| circuit.name == train.circuit)
+                .ok_or_else(|| ScenarioError::UnknownCircuit(train.circuit.clone()))?;
+            if configured[circuit] {
+                return Err(ScenarioError::DuplicateCircuit(train.circuit.clone()));
+            }
+            configured[circuit] = true;
+
+            let setup = &mut setups[circuit];
+            if let Some(start) = &train.start {
+                setup.start_step = layout.circuits[circuit]
+                    .route
+                    .iter()
+                    .position(|step| step.first() == Some(start))
+                    .ok_or_else(|| ScenarioError::InvalidStart {
+                        circuit: train.circuit.clone(),
+                        segment: start.clone(),
+                    })?;
+            }
+            setup.min_velocity = train.min_velocity.unwrap_or(setup.min_velocity);
+            setup.max_velocity = train.max_velocity.unwrap_or(setup.max_velocity);
+            let (min, max) = (setup.min_velocity, setup.max_velocity);
+            if !(0.0 <= min && min <= max && max.is_finite()) {
+                return Err(ScenarioError::InvalidVelocityLimits(train.circuit.clone()));
+            }
+            // sem velocidade no cenário, a padrão acompanha os novos limites
+            setup.velocity = match train.velocity {
+                Some(velocity) => velocity,
+                None => setup
+                    .velocity
+                    .max(setup.min_velocity)
+                    .min(setup.max_velocity),
+            };
+            if !(setup.min_velocity..=setup.max_velocity).contains(&setup.velocity) {
+                return Err(ScenarioError::InvalidVelocity(train.circuit.clone()));
+            }
+            setup.priority = train.priority.unwrap_or(setup.priority);
+            setup.enabled = train.enabled;
+        }
+        Ok(setups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> Layout {
+        Layout::from_ron(include_str!("../assets/layouts/two_trains.ron")).unwrap()
+    }
+
+    fn scenario(trains: &str) -> Scenario {
+        let source = format!(
+            "#![enable(implicit_some)]\n(name: \"teste\", trains: [{}])",
+            trains
+        );
+        Scenario::from_ron(&source).unwrap()
+    }
+
+    #[test]
+    fn empty_scenario_keeps_the_defaults() {
+        let layout = layout();
+        let setups = scenario("").setups(&layout, &[2.0]).unwrap();
+        assert_eq!(setups, TrainSetup::defaults(&layout, &[2.0]));
+    }
+
+    #[test]
+    fn start_picks_the_step_it_opens() {
+        let setups = scenario("(circuit: \"south\", start: \"S2\", priority: 3, enabled: false)")
+            .setups(&layout(), &[])
+            .unwrap();
+        assert_eq!(setups[0].start_step, 0);
+        assert_eq!(setups[1].start_step, 2);
+        assert_eq!(setups[1].priority, 3);
+        assert!(!setups[1].enabled);
+    }
+
+    #[test]
+    fn default_velocity_follows_new_limits() {
+        let setups = scenario("(circuit: \"north\", min_velocity: 3.0, max_velocity: 4.0)")
+            .setups(&layout(), &[1.0])
+            .unwrap();
+        assert_eq!(setups[0].velocity, 3.0);
+    }
+
+    #[test]
+    fn rejects_scenarios_that_do_not_fit_the_layout() {
+        let layout = layout();
+        let error = |trains: &str| scenario(trains).setups(&layout, &[]).unwrap_err();
+
+        assert!(matches!(
+            error("(circuit: \"east\")"),
+            ScenarioError::UnknownCircuit(circuit) if circuit == "east"
+        ));
+        assert!(matches!(
+            error("(circuit: \"north\"), (circuit: \"north\")"),
+            ScenarioError::DuplicateCircuit(_)
+        ));
+        // S1 não está na rota do norte
+        assert!(matches!(
+            error("(circuit: \"north\", start: \"S1\")"),
+            ScenarioError::InvalidStart { .. }
+        ));
+        assert!(matches!(
+            error("(circuit: \"north\", min_velocity: 5.0, max_velocity: 2.0)"),
+            ScenarioError::InvalidVelocityLimits(_)
+        ));
+        assert!(matches!(
+            error("(circuit: \"north\", velocity: 9.0)"),
+            ScenarioError::InvalidVelocity(_)
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+use std::{fs, path::Path};
+use tracks::{circuit::Layout, Scenario, ScenarioError, Simulation, TrainSetup};
+
+pub struct ScenarioPlugin;
+
+/// Cenários que servem para a malha aberta; o primeiro é o padrão.
+pub struct ScenarioList {
+    scenarios: Vec<(String, Vec<TrainSetup>)>,
+    current: usize,
+    open: bool,
+    /// Arquivos que não puderam ser lidos, com o erro, avisados na largada.
+    invalid: Vec<String>,
+}
+
+impl ScenarioList {
+    /// Lê os cenários de `dir` que a malha aceita; arquivos que não são
+    /// cenários válidos ficam de fora e são avisados no log quando o app
+    /// abre. `current` é o cenário escolhido na linha de comando, que entra
+    /// na lista se ainda não estiver, e o erro é o dele se não servir para a
+    /// malha.
+    pub fn load<P: AsRef<Path>>(
+        dir: P,
+        layout: &Layout,
+        velocities: &[f64],
+        current: Option<&Scenario>,
+    ) -> Result<ScenarioList, ScenarioError> {
+        let mut scenarios = vec![(
+            "padrão".to_string(),
+            TrainSetup::defaults(layout, velocities),
+        )];
+
+        let mut invalid = Vec::new();
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default();
+        paths.sort();
+        for path in paths {
+            if path.extension().and_then(|extension| extension.to_str()) != Some("ron") {
+                continue;
+            }
+            let scenario = match Scenario::load(&path) {
+                Ok(scenario) => scenario,
+                Err(error) => {
+                    invalid.push(format!("{}: {}", path.display(), error));
+                    continue;
+                }
+            };
+            // cenários de outras malhas não aparecem na lista
+            if let Ok(setups) = scenario.setups(layout, velocities) {
+                scenarios.push((scenario.name, setups));
+            }
+        }
+
+        let current = match current {
+            Some(scenario) => {
+                let setups = scenario.setups(layout, velocities)?;
+                scenarios
+                    .iter()
+                    .position(|(name, _)| *name == scenario.name)
+                    .unwrap_or_else(|| {
+                        scenarios.push((scenario.name.clone(), setups));
+                        scenarios.len() - 1
+                    })
+            }
+            None => 0,
+        };
+
+        Ok(ScenarioList {
+            scenarios,
+            current,
+            open: false,
+            invalid,
+        })
+    }
+}
+
+fn warn_invalid_scenarios(scenarios: Res<ScenarioList>) {
+    for error in &scenarios.invalid {
+        warn!("{}", error);
+    }
+}
+
+#[derive(Component)]
+struct ScenarioHeader;
+
+#[derive(Component)]
+struct ScenarioMenu;
+
+#[derive(Component)]
+struct ScenarioOption(usize);
+
+const MENU_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+const HOVER_COLOR: Color = Color::rgb(0.45, 0.45, 0.45);
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(spawn_scenario_menu)
+            .add_startup_system(warn_invalid_scenarios)
+            .add_system(press_scenario_button)
+            .add_system(scenario_menu_update);
+    }
+}
+
+fn header_text(list: &ScenarioList) -> String {
+    format!("Cenário: {}", list.scenarios[list.current].0)
+}
+
+fn spawn_scenario_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    list: Res<ScenarioList>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font,
+        font_size: 16.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    let button_style = Style {
+        size: Size::new(Val::Percent(100.0), Val::Px(28.0)),
+        margin: Rect::all(Val::Px(1.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..Default::default()
+    };
+
+    // no canto de cima do painel de botões, abrindo para baixo
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.0),
+                    top: Val::Px(10.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(220.0), Val::Undefined),
+                flex_direction: FlexDirection::ColumnReverse,
+                ..Default::default()
+            },
+            color: UiColor(Color::NONE),
+            ..Default::default()
+        })
+        .with_children(|menu| {
+            menu.spawn_bundle(ButtonBundle {
+                style: button_style.clone(),
+                color: UiColor(MENU_COLOR),
+                ..Default::default()
+            })
+            .insert(ScenarioHeader)
+            .with_children(|button| {
+                button.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        header_text(&list),
+                        text_style.clone(),
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                });
+            });
+
+            menu.spawn_bundle(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::ColumnReverse,
+                    display: Display::None,
+                    ..Default::default()
+                },
+                color: UiColor(Color::NONE),
+                ..Default::default()
+            })
+            .insert(ScenarioMenu)
+            .with_children(|options| {
+                for (index, (name, _)) in list.scenarios.iter().enumerate() {
+                    options
+                        .spawn_bundle(ButtonBundle {
+                            style: button_style.clone(),
+                            color: UiColor(MENU_COLOR),
+                            ..Default::default()
+                        })
+                        .insert(ScenarioOption(index))
+                        .with_children(|button| {
+                            button.spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    name.clone(),
+                                    text_style.clone(),
+                                    Default::default(),
+                                ),
+                                ..Default::default()
+                            });
+                        });
+                }
+            });
+        });
+}
+
+/// Botões do menu: o cabeçalho (sem [`ScenarioOption`]) e as opções.
+type MenuButtons<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        &'static mut UiColor,
+        Option<&'static ScenarioOption>,
+    ),
+    (
+        Changed<Interaction>,
+        Or<(With<ScenarioHeader>, With<ScenarioOption>)>,
+    ),
+>;
+
+fn press_scenario_button(
+    mut buttons: MenuButtons,
+    mut list: ResMut<ScenarioList>,
+    mut simulation: ResMut<Simulation>,
+) {
+    buttons.for_each_mut(
+        |(interaction, mut color, option)| match (*interaction, option) {
+            (Interaction::Clicked, None) => list.open = !list.open,
+            (Interaction::Clicked, Some(option)) => {
+                list.open = false;
+                match simulation.set_setups(list.scenarios[option.0].1.clone()) {
+                    Ok(()) => list.current = option.0,
+                    Err(error) => warn!("{}", error),
+                }
+            }
+            (Interaction::Hovered, _) => *color = UiColor(HOVER_COLOR),
+            (Interaction::None, _) => *color = UiColor(MENU_COLOR),
+        },
+    );
+}
+
+fn scenario_menu_update(
+    list: Res<ScenarioList>,
+    mut menus: Query<&mut Style, With<ScenarioMenu>>,
+    headers: Query<&Children, With<ScenarioHeader>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !list.is_changed() {
+        return;
+    }
+    menus.for_each_mut(|mut style| {
+        style.display = if list.open {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    });
+    headers.for_each(|children| {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = header_text(&list);
+            }
+        }
+    });
+}
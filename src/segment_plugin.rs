@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use tracks::Simulation;
+
+use crate::{setup_plugin::CONTOUR_SIZE, TrackMaterials, TrainMaterials, UiTrackPos};
+
+/// Cor do contorno de um trilho com trens esperando por ele.
+const CONTENDED_COLOR: Color = Color::rgb(0.9, 0.1, 0.1);
+
+pub struct SegmentPlugin;
+
+/// Pedaço reto do desenho de um trilho, colorido conforme o estado do lock.
+#[derive(Component)]
+struct SegmentSprite {
+    segment: usize,
+    free: Color,
+}
+
+/// Contorno em volta do [`SegmentSprite`], visível quando há trens esperando.
+#[derive(Component)]
+struct SegmentOutline(usize);
+
+impl Plugin for SegmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(segment_spawn)
+            .add_system(segment_update.after(segment_spawn));
+    }
+}
+
+/// Desenha cada trilho por cima do contorno do primeiro circuito que o usa;
+/// refeito sempre que o [`UiTrackPos`] é recalculado.
+fn segment_spawn(
+    mut commands: Commands,
+    ui_tracks: Res<UiTrackPos>,
+    simulation: Res<Simulation>,
+    materials: Res<Assets<ColorMaterial>>,
+    track_materials: Res<TrackMaterials>,
+    query: Query<Entity, With<SegmentSprite>>,
+) {
+    if !ui_tracks.is_changed() {
+        return;
+    }
+    query.for_each(|entity| commands.entity(entity).despawn_recursive());
+
+    let band = CONTOUR_SIZE / 2.0;
+    for segment in 0..simulation.layout.segments.len() {
+        let Some(&circuit) = simulation.layout.users(segment).first() else {
+            continue;
+        };
+        let free = materials
+            .get(track_materials.materials[circuit].clone())
+            .unwrap()
+            .color;
+
+        for (start, end) in ui_tracks.loops[circuit].pieces(segment) {
+            // um pequeno vão entre trilhos vizinhos mostra onde cada um termina
+            let length = (end - start).abs();
+            let size = if length.x > length.y {
+                Vec2::new((length.x - 2.0).max(1.0), band)
+            } else {
+                Vec2::new(band, (length.y - 2.0).max(1.0))
+            };
+
+            commands
+                .spawn_bundle(SpriteBundle {
+                    transform: Transform {
+                        translation: ((start + end) / 2.0).extend(20.0),
+                        ..Default::default()
+                    },
+                    sprite: Sprite {
+                        custom_size: Some(size),
+                        color: free,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(SegmentSprite { segment, free })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(SpriteBundle {
+                            transform: Transform {
+                                translation: Vec3::new(0.0, 0.0, -0.5),
+                                ..Default::default()
+                            },
+                            sprite: Sprite {
+                                custom_size: Some(size + Vec2::splat(6.0)),
+                                color: CONTENDED_COLOR,
+                                ..Default::default()
+                            },
+                            visibility: Visibility { is_visible: false },
+                            ..Default::default()
+                        })
+                        .insert(SegmentOutline(segment));
+                });
+        }
+    }
+}
+
+/// Livre: cor do circuito; travado: cor do trem que está com o lock;
+/// disputado: contorno vermelho.
+fn segment_update(
+    simulation: Res<Simulation>,
+    materials: Res<Assets<ColorMaterial>>,
+    train_materials: Res<TrainMaterials>,
+    mut sprites: Query<(&SegmentSprite, &mut Sprite)>,
+    mut outlines: Query<(&SegmentOutline, &mut Visibility)>,
+) {
+    let segments = simulation.layout.segments.len();
+    let holders: Vec<Option<u64>> = (0..segments)
+        .map(|segment| simulation.tracker.holder(segment))
+        .collect();
+    let contended: Vec<bool> = (0..segments)
+        .map(|segment| !simulation.tracker.waiting_for(segment).is_empty())
+        .collect();
+
+    sprites.for_each_mut(|(segment, mut sprite)| {
+        sprite.color = match holders[segment.segment] {
+            Some(train) => {
+                let material = train_materials.materials[train as usize].clone();
+                materials.get(material).unwrap().color
+            }
+            None => segment.free,
+        };
+    });
+    outlines.for_each_mut(|(outline, mut visibility)| {
+        visibility.is_visible = contended[outline.0];
+    });
+}
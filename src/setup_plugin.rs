@@ -1,17 +1,20 @@
-use std::sync::{Arc, Mutex};
+use bevy::{prelude::*, window::WindowResized};
+use tracks::Simulation;
 
-use bevy::prelude::*;
-use tracks::Track;
-
-use crate::{HalfWindowSize, TrackMaterials, TrainMaterials, UiTrackPos};
+use crate::{
+    layout_engine, loop_path::LoopPath, HalfWindowSize, TrackMaterials, TrainMaterials, UiTrackPos,
+    PANEL_WIDTH,
+};
 pub struct SetupPlugin;
 
 impl Plugin for SetupPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup).add_startup_stage(
-            "spawn_track_entities",
-            SystemStage::single(spawn_track_entities),
-        );
+        app.add_startup_system(setup)
+            .add_startup_stage(
+                "spawn_track_entities",
+                SystemStage::single(spawn_track_entities),
+            )
+            .add_system(relayout_tracks);
     }
 }
 
@@ -19,6 +22,7 @@ fn setup(
     mut commands: Commands,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut windows: ResMut<Windows>,
+    simulation: Res<Simulation>,
 ) {
     // camera
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
@@ -29,210 +33,170 @@ fn setup(
     window.set_position(IVec2::new(3870, 4830));
 
     let gray_scale = 90.2 / 100.0;
-    let green_train_color = Color::rgb(7.1 / 100.0, 71.8 / 100.0, 58.8 / 100.0);
-    let green_track_color = Color::rgb(56.5 / 100.0, 94.1 / 100.0, 85.1 / 100.0);
-
-    let purple_train_color = Color::rgb(51.1 / 100.0, 55.3 / 100.0, 86.3 / 100.0);
-    let purple_track_color = Color::rgb(85.1 / 100.0, 83.9 / 100.0, 92.9 / 100.0);
-
-    let red_train_color = Color::rgb(1.0, 14.9 / 100.0, 8.2 / 100.0);
-    let red_track_color = Color::rgb(96.5 / 100.0, 72.5 / 100.0, 16.1 / 100.0);
-
-    let blue_train_color = Color::rgb(2.0 / 100.0, 42.7 / 100.0, 74.5 / 100.0);
-    let blue_track_color = Color::rgb(6.7 / 100.0, 65.1 / 100.0, 94.5 / 100.0);
-
     let background_color = Color::rgb(gray_scale, gray_scale, gray_scale);
 
     commands.insert_resource(ClearColor(background_color.into()));
 
-    commands.insert_resource(TrainMaterials {
-        green_train_material: materials.add(green_train_color.into()),
-        purple_train_material: materials.add(purple_train_color.into()),
-        red_train_material: materials.add(red_train_color.into()),
-        blue_train_material: materials.add(blue_train_color.into()),
-        train_block_size: 50.0,
-    });
+    let circuits = &simulation.layout.circuits;
+    let train_colors: Vec<Color> = circuits
+        .iter()
+        .enumerate()
+        .map(|(i, circuit)| match circuit.rgb() {
+            Some([r, g, b]) => Color::rgb(r, g, b),
+            None => Color::hsl(360.0 * i as f32 / circuits.len() as f32, 0.7, 0.45),
+        })
+        .collect();
 
     commands.insert_resource(TrackMaterials {
-        green_track_material: materials.add(green_track_color.into()),
-        purple_track_material: materials.add(purple_track_color.into()),
-        red_track_material: materials.add(red_track_color.into()),
-        blue_track_material: materials.add(blue_track_color.into()),
+        materials: train_colors
+            .iter()
+            .map(|color| materials.add(track_color(*color).into()))
+            .collect(),
         background_material: materials.add(background_color.into()),
     });
 
+    commands.insert_resource(TrainMaterials {
+        materials: train_colors
+            .into_iter()
+            .map(|color| materials.add(color.into()))
+            .collect(),
+    });
+
     commands.insert_resource(HalfWindowSize {
         width: window.width() / 2.0,
         height: window.height() / 2.0,
     })
 }
 
+/// O trilho é desenhado num tom mais claro que o trem.
+fn track_color(train_color: Color) -> Color {
+    let [r, g, b, _] = train_color.as_rgba_f32();
+    let lighten = |channel: f32| channel + 0.6 * (1.0 - channel);
+    Color::rgb(lighten(r), lighten(g), lighten(b))
+}
+
+/// Largura da faixa colorida de cada circuito somada à do fundo interno.
+pub const CONTOUR_SIZE: f32 = 30.0;
+
+/// Parte interna (cor de fundo) do contorno de um circuito.
+#[derive(Component)]
+struct LoopInner(usize);
+
+/// Faixa colorida de um circuito, filha do [`LoopInner`].
+#[derive(Component)]
+struct LoopBand;
+
+/// Recalcula contornos, rótulos e tamanhos para o tamanho atual da janela.
+fn track_positions(simulation: &Simulation, half_window_size: &HalfWindowSize) -> UiTrackPos {
+    // os circuitos ficam à direita dos botões, abaixo do título
+    let (width, height) = (half_window_size.width, half_window_size.height);
+    let panel = 2.0 * width * PANEL_WIDTH / 100.0;
+    let area_min = Vec2::new(-width + panel, -height) + Vec2::splat(CONTOUR_SIZE);
+    let area_max = Vec2::new(width, height - 180.0) - Vec2::splat(CONTOUR_SIZE);
+
+    let drawing = layout_engine::compute(&simulation.layout, area_min, area_max);
+    let cell = drawing.cell;
+    UiTrackPos {
+        track_pos: drawing
+            .labels
+            .into_iter()
+            .map(|(point, name)| (point.extend(100.0), name))
+            .collect(),
+        label_size: (0.15 * cell.min_element()).min(45.0),
+        title_pos: Vec2::new((area_min.x + area_max.x) / 2.0, height - 60.0),
+        train_size: (0.2 * cell.min_element()).min(50.0),
+        loops: drawing.loops,
+    }
+}
+
+/// O trem anda no meio da faixa colorida, que fica centrada no contorno.
+fn band_size(path: &LoopPath) -> Vec2 {
+    path.size() + Vec2::splat(CONTOUR_SIZE / 2.0)
+}
+
 fn spawn_track_entities(
     mut commands: Commands,
     materials: Res<Assets<ColorMaterial>>,
     track_materials: Res<TrackMaterials>,
-    train_materials: Res<TrainMaterials>,
     half_window_size: Res<HalfWindowSize>,
+    simulation: Res<Simulation>,
 ) {
     println!("spawn_track_entities!!");
 
-    let train_block_size = train_materials.train_block_size;
-    let contour_size = 30.0;
-    let blue_track_size = Vec2::new(
-        7.0 * half_window_size.width / 5.0,
-        2.0 * half_window_size.height / 3.0,
-    );
-    let blue_track_pos = Vec3::new(
-        -train_block_size - blue_track_size.x / 2.0 + half_window_size.width,
-        -half_window_size.height + blue_track_size.y / 2.0 + half_window_size.height / 5.0,
-        10.0,
-    );
-
     let background_color = materials
         .get(track_materials.background_material.clone())
-        .clone()
         .unwrap()
         .color;
-    // let color = ;
-    commands
-        .spawn_bundle(SpriteBundle {
-            // material: track_materials.background_material.clone(),
-            transform: Transform {
-                translation: blue_track_pos,
-                ..Default::default()
-            },
-            sprite: Sprite {
-                custom_size: Some(Vec2::new(
-                    blue_track_size.x - contour_size,
-                    blue_track_size.y - contour_size,
-                )),
-                color: background_color.clone(),
-                ..Default::default()
-            },
-            ..Default::default()
-        })
-        .with_children(|parent| {
-            parent.spawn_bundle(SpriteBundle {
-                // material: track_materials.blue_track_material.clone(),
-                sprite: Sprite {
-                    custom_size: Some(blue_track_size),
-                    color: materials
-                        .get(track_materials.blue_track_material.clone())
-                        .clone()
-                        .unwrap()
-                        .color,
-                    ..Default::default()
-                },
-                ..Default::default()
-            });
-        });
-
-    let material_tracks = vec![
-        track_materials.green_track_material.clone(),
-        track_materials.purple_track_material.clone(),
-        track_materials.red_track_material.clone(),
-    ];
-
-    let off_set = 50.0;
-    let small_track_size = Vec2::new((blue_track_size.x - 2.0 * off_set) / 3.0, blue_track_size.y);
-    let mut pos = Vec3::new(-blue_track_pos.x - contour_size - 1.0, 100.0, 10.0);
-    let ui_l1_pos = Vec3::new(pos.x - 0.75 * small_track_size.x / 2.0, pos.y, 100.0);
-    let ui_l2_pos = Vec3::new(pos.x, pos.y + 1.10 * small_track_size.y / 2.0, 100.0);
-    let ui_l3_pos = Vec3::new(ui_l1_pos.x + 0.97 * small_track_size.x, pos.y, 100.0); //
-    let ui_l4_pos = Vec3::new(pos.x, pos.y - 1.10 * small_track_size.y / 2.0, 100.0); //
-    let ui_l5_pos = Vec3::new(ui_l3_pos.x + small_track_size.x + off_set, pos.y, 100.0);
-    let ui_l6_pos = Vec3::new(
-        ui_l2_pos.x + small_track_size.x + off_set,
-        pos.y - 1.10 * small_track_size.y / 2.0,
-        100.0,
-    );
-    let ui_l7_pos = Vec3::new(
-        ui_l2_pos.x + small_track_size.x + off_set,
-        pos.y + 1.10 * small_track_size.y / 2.0,
-        100.0,
-    );
-    let ui_l8_pos = Vec3::new(
-        ui_l7_pos.x + small_track_size.x + off_set,
-        pos.y + 1.10 * small_track_size.y / 2.0,
-        100.0,
-    );
-    let ui_l9_pos = Vec3::new(ui_l5_pos.x + small_track_size.x + off_set, pos.y, 100.0);
-    let ui_l10_pos = Vec3::new(
-        ui_l7_pos.x + small_track_size.x + off_set,
-        pos.y - 1.10 * small_track_size.y / 2.0,
-        100.0,
-    );
-    let ui_l11_pos = Vec3::new(
-        blue_track_pos.x - 0.90 * blue_track_size.x / 2.0,
-        blue_track_pos.y,
-        100.0,
-    );
-    let ui_l12_pos = Vec3::new(
-        blue_track_pos.x + 0.90 * blue_track_size.x / 2.0,
-        blue_track_pos.y,
-        100.0,
-    );
-    let ui_l13_pos = Vec3::new(
-        blue_track_pos.x,
-        blue_track_pos.y - 0.75 * blue_track_size.y / 2.0,
-        100.0,
-    );
-
-    for material in material_tracks {
 
+    let ui_tracks = track_positions(&simulation, &half_window_size);
+    for (index, (path, material)) in ui_tracks
+        .loops
+        .iter()
+        .zip(&track_materials.materials)
+        .enumerate()
+    {
+        let track_size = band_size(path);
         commands
             .spawn_bundle(SpriteBundle {
                 transform: Transform {
-                    translation: pos,
+                    translation: path.center().extend(10.0),
                     ..Default::default()
                 },
                 sprite: Sprite {
-                    custom_size: Some(Vec2::new(
-                        small_track_size.x - contour_size,
-                        small_track_size.y - contour_size,
-                    )),
-                    color: background_color.clone(),
+                    custom_size: Some(track_size - Vec2::splat(CONTOUR_SIZE)),
+                    color: background_color,
                     ..Default::default()
                 },
                 ..Default::default()
             })
+            .insert(LoopInner(index))
             .with_children(|parent| {
-                parent.spawn_bundle(SpriteBundle {
-                    sprite: Sprite {
-                        custom_size: Some(small_track_size),
-                        color: materials.get(material.clone()).unwrap().color,
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(track_size),
+                            color: materials.get(material.clone()).unwrap().color,
+                            ..Default::default()
+                        },
                         ..Default::default()
-                    },
-                    ..Default::default()
-                });
+                    })
+                    .insert(LoopBand);
             });
-        pos = Vec3::new(pos.x + small_track_size.x + off_set, pos.y, 10.0);
     }
 
-    let ui_tracks_pos = vec![
-        (ui_l1_pos, "L1".to_string()),
-        (ui_l2_pos, "L2".to_string()),
-        (ui_l3_pos, "L3".to_string()),
-        (ui_l4_pos, "L4".to_string()),
-        (ui_l5_pos, "L5".to_string()),
-        (ui_l6_pos, "L6".to_string()),
-        (ui_l7_pos, "L7".to_string()),
-        (ui_l8_pos, "L8".to_string()),
-        (ui_l9_pos, "L9".to_string()),
-        (ui_l10_pos, "L10".to_string()),
-        (ui_l11_pos, "L11".to_string()),
-        (ui_l12_pos, "L12".to_string()),
-        (ui_l13_pos, "L13".to_string()),
-    ];
-
-    commands.insert_resource(UiTrackPos {
-        track_pos: ui_tracks_pos,
-        center_blue_track: Vec2::new(blue_track_pos.x, blue_track_pos.y),
-    });
-
-    let tracks: Vec<Arc<Mutex<Track>>> = (1..14)
-        .map(|i| Arc::new(Mutex::new(Track::new(format!("L{}", i).to_string(), 5))))
-        .collect();
+    commands.insert_resource(ui_tracks);
+}
 
-    commands.insert_resource(tracks);
+/// Refaz o desenho quando a janela muda de tamanho; trens e rótulos seguem
+/// o [`UiTrackPos`] novo.
+fn relayout_tracks(
+    mut resized: EventReader<WindowResized>,
+    mut half_window_size: ResMut<HalfWindowSize>,
+    mut ui_tracks: ResMut<UiTrackPos>,
+    simulation: Res<Simulation>,
+    mut inner_query: Query<(&LoopInner, &mut Transform, &mut Sprite, &Children)>,
+    mut band_query: Query<&mut Sprite, (With<LoopBand>, Without<LoopInner>)>,
+) {
+    let Some(event) = resized.iter().rev().find(|event| event.id.is_primary()) else {
+        return;
+    };
+
+    *half_window_size = HalfWindowSize {
+        width: event.width / 2.0,
+        height: event.height / 2.0,
+    };
+    *ui_tracks = track_positions(&simulation, &half_window_size);
+
+    inner_query.for_each_mut(|(inner, mut transform, mut sprite, children)| {
+        let path = &ui_tracks.loops[inner.0];
+        let track_size = band_size(path);
+        transform.translation = path.center().extend(transform.translation.z);
+        sprite.custom_size = Some(track_size - Vec2::splat(CONTOUR_SIZE));
+
+        for child in children.iter() {
+            if let Ok(mut band) = band_query.get_mut(*child) {
+                band.custom_size = Some(track_size);
+            }
+        }
+    });
 }
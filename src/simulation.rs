@@ -0,0 +1,183 @@
+use crate::{
+    circuit::{Circuit, Layout, SyncKind},
+    simulation_core::SimulationCore,
+    train_machine::TrainMachine,
+    Command, Deadlock, Error, LockRecover, Track, TrainControl, TrainSetup,
+};
+use std::{
+    ops::Deref,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Quem faz os trens andarem.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Executor {
+    /// Uma thread por trem, iniciadas em [`Simulation::spawn`].
+    #[default]
+    Threads,
+    /// Nenhuma thread: quem usa a simulação faz os trens andarem no próprio
+    /// laço, por exemplo avançando as máquinas de [`Simulation::machines`].
+    Stepped,
+}
+
+/// Trilhos, circuitos e trens montados a partir de uma malha; os controles
+/// vêm do [`SimulationCore`].
+pub struct Simulation {
+    core: SimulationCore,
+    pub circuits: Vec<Arc<dyn Circuit + Send + Sync>>,
+    sync: SyncKind,
+    executor: Executor,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Simulation {
+    /// O trem `i` percorre o circuito `i` com a velocidade `velocities[i]`
+    /// (a mínima se não houver), limitada pelos limites do circuito.
+    pub fn new(layout: Layout, sync: SyncKind, velocities: &[f64]) -> Result<Simulation, Error> {
+        let setups = TrainSetup::defaults(&layout, velocities);
+        Simulation::with_setups(layout, sync, setups)
+    }
+
+    /// O trem `i` começa como descrito em `setups[i]`, ver [`crate::Scenario`].
+    pub fn with_setups(
+        layout: Layout,
+        sync: SyncKind,
+        setups: Vec<TrainSetup>,
+    ) -> Result<Simulation, Error> {
+        let core = SimulationCore::new(layout, sync, setups)?;
+        let circuits: Vec<Arc<dyn Circuit + Send + Sync>> = core
+            .layout
+            .circuits(&core.tracks, &core.interlocking, &core.events)?
+            .into_iter()
+            .zip(core.setups())
+            .map(|(mut circuit, setup)| {
+                circuit.start_at(setup.start_step)?;
+                Ok(Arc::new(circuit) as Arc<dyn Circuit + Send + Sync>)
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Simulation {
+            core,
+            circuits,
+            sync,
+            executor: Executor::default(),
+            threads: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn sync(&self) -> SyncKind {
+        self.sync
+    }
+
+    pub fn executor(&self) -> Executor {
+        self.executor
+    }
+
+    /// Vale a partir do próximo [`Simulation::spawn`] e continua valendo
+    /// depois de [`Simulation::restart`].
+    pub fn set_executor(&mut self, executor: Executor) {
+        self.executor = executor;
+    }
+
+    /// Máquinas de estado dos trens ligados, começando do estado inicial,
+    /// para avançar com [`SimulationCore::network`] no modo [`Executor::Stepped`].
+    pub fn machines(&self) -> Vec<TrainMachine> {
+        TrainMachine::from_setups(&self.layout, self.setups())
+    }
+
+    /// Coloca cada trem para rodar na sua própria thread, até [`Simulation::stop`].
+    /// Com [`Executor::Stepped`] ou com as threads já rodando não faz nada.
+    pub fn spawn(&self) {
+        if self.executor == Executor::Stepped {
+            return;
+        }
+        let mut controls = self.core.controls.lock_recover();
+        let mut threads = self.threads.lock_recover();
+        // já rodando: há um canal de controle por trem até o stop
+        if !controls.is_empty() {
+            return;
+        }
+
+        for (i, ((circuit, train), position)) in self
+            .circuits
+            .iter()
+            .zip(&self.trains)
+            .zip(&self.positions)
+            .enumerate()
+        {
+            let (sender, mut control) = TrainControl::channel();
+            if self.is_paused(i) {
+                // o receptor acabou de ser criado, então o envio não falha
+                let _ = sender.send(Command::Pause);
+            }
+            controls.push(sender);
+            if !self.is_enabled(i) {
+                continue;
+            }
+
+            let circuit = circuit.clone();
+            let train = train.clone();
+            let position = position.clone();
+            threads.push(thread::spawn(move || {
+                while circuit.run(position.clone(), &train, &mut control).is_ok() {}
+            }));
+        }
+    }
+
+    /// Para todas as threads, liberando os trilhos, e espera elas terminarem.
+    pub fn stop(&self) {
+        for sender in self.core.controls.lock_recover().drain(..) {
+            let _ = sender.send(Command::Stop);
+        }
+        for thread in self.threads.lock_recover().drain(..) {
+            let _ = thread.join();
+        }
+    }
+
+    /// Para os trens e recomeça a simulação do estado inicial.
+    pub fn restart(&mut self) -> Result<(), Error> {
+        self.set_setups(self.setups().to_vec())
+    }
+
+    /// Recomeça a simulação com outro cenário. A nova é montada antes de a
+    /// atual parar, então com `setups` inválidos nada muda.
+    pub fn set_setups(&mut self, setups: Vec<TrainSetup>) -> Result<(), Error> {
+        let mut next = Simulation::with_setups(self.layout.clone(), self.sync, setups)?;
+        next.executor = self.executor;
+        next.set_time_scale(self.time_scale())?;
+        next.set_grant_policy(self.grant_policy());
+        self.stop();
+        *self = next;
+        self.spawn();
+        Ok(())
+    }
+
+    /// Roda os trens por `duration` de tempo simulado sem janela, chamando
+    /// `on_enter` com o instante, o trem e o trilho sempre que um trem muda
+    /// de trilho. Devolve os deadlocks em que os trens estavam no fim.
+    pub fn run_for<F>(&self, duration: Duration, on_enter: F) -> Vec<Deadlock>
+    where
+        F: FnMut(Duration, usize, &Track),
+    {
+        self.spawn();
+        let deadlocks = self.core.watch(duration, on_enter);
+        self.stop();
+        deadlocks
+    }
+}
+
+impl Deref for Simulation {
+    type Target = SimulationCore;
+
+    fn deref(&self) -> &SimulationCore {
+        &self.core
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{
+    circuit::{GrantPolicy, Interlocking, Layout, SyncKind},
+    track::TrainPosition,
+    train_machine::Network,
+    Command, Deadlock, Error, EventKind, EventLog, LockRecover, LockTracker, Track, Train,
+    TrainSetup,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Intervalo em que o modo sem janela confere a posição dos trens.
+const POLL: Duration = Duration::from_millis(5);
+
+/// O que todos os executores dividem: trilhos, intertravamento e trens, com
+/// os controles de velocidade, tempo, ordem de entrega e pausa. A
+/// [`crate::Simulation`] e a [`crate::PoolSimulation`] só decidem quem faz
+/// os trens andarem.
+pub struct SimulationCore {
+    pub layout: Layout,
+    pub tracks: Vec<Arc<Track>>,
+    pub tracker: Arc<LockTracker>,
+    pub events: Arc<EventLog>,
+    pub interlocking: Arc<Interlocking>,
+    pub trains: Vec<Arc<Mutex<Train>>>,
+    pub positions: Vec<Arc<Mutex<TrainPosition>>>,
+    setups: Vec<TrainSetup>,
+    paused: Vec<AtomicBool>,
+    /// Canal de cada trem quando ele roda na própria thread, até o stop.
+    pub(crate) controls: Mutex<Vec<Sender<Command>>>,
+}
+
+impl SimulationCore {
+    /// O trem `i` começa como descrito em `setups[i]`, ver [`crate::Scenario`].
+    pub fn new(
+        layout: Layout,
+        sync: SyncKind,
+        setups: Vec<TrainSetup>,
+    ) -> Result<SimulationCore, Error> {
+        check_setups(&layout, &setups)?;
+        let tracks = layout.tracks()?;
+        let tracker = Arc::new(layout.lock_tracker());
+        let events = Arc::new(layout.event_log());
+        let interlocking = Arc::new(layout.interlocking(sync, tracker.clone(), events.clone()));
+        for (train, setup) in setups.iter().enumerate() {
+            interlocking.set_priority(train as u64, setup.priority);
+        }
+
+        let trains = setups
+            .iter()
+            .enumerate()
+            .map(|(i, setup)| Ok(Arc::new(Mutex::new(setup.train(i as u64)?))))
+            .collect::<Result<_, Error>>()?;
+        let positions = setups
+            .iter()
+            .enumerate()
+            .map(|(i, setup)| {
+                let start = layout.route(i)[setup.start_step][0];
+                Arc::new(Mutex::new(TrainPosition::new(start)))
+            })
+            .collect();
+
+        Ok(SimulationCore {
+            paused: setups.iter().map(|_| AtomicBool::new(false)).collect(),
+            layout,
+            tracks,
+            tracker,
+            events,
+            interlocking,
+            trains,
+            positions,
+            setups,
+            controls: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn network(&self) -> Network<'_> {
+        Network {
+            tracks: &self.tracks,
+            interlocking: &self.interlocking,
+            events: &self.events,
+            trains: &self.trains,
+            positions: &self.positions,
+        }
+    }
+
+    pub fn setups(&self) -> &[TrainSetup] {
+        &self.setups
+    }
+
+    /// Trens desligados pelo cenário ficam parados fora da malha.
+    pub fn is_enabled(&self, train: usize) -> bool {
+        self.setups.get(train).is_some_and(|setup| setup.enabled)
+    }
+
+    pub fn increment(&self, train: usize) -> Result<(), Error> {
+        self.change_velocity(train, Train::increment)
+    }
+
+    pub fn decrement(&self, train: usize) -> Result<(), Error> {
+        self.change_velocity(train, Train::decrement)
+    }
+
+    pub fn set_velocity(&self, train: usize, velocity: f64) -> Result<(), Error> {
+        self.change_velocity(train, |train| train.set_velocity(velocity))
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.events.clock().scale()
+    }
+
+    /// Acelera ou desacelera todos os trens, ver [`crate::Clock::set_scale`].
+    pub fn set_time_scale(&self, scale: f64) -> Result<(), Error> {
+        self.events.clock().set_scale(scale)
+    }
+
+    pub fn grant_policy(&self) -> GrantPolicy {
+        self.interlocking.policy()
+    }
+
+    /// Ordem de entrega dos trilhos disputados, ver [`GrantPolicy`];
+    /// continua valendo depois de [`crate::Simulation::restart`].
+    pub fn set_grant_policy(&self, policy: GrantPolicy) {
+        self.interlocking.set_policy(policy);
+    }
+
+    /// Para o trem onde ele estiver; enquanto pausado ele não recebe trilhos novos.
+    pub fn pause(&self, train: usize) -> Result<(), Error> {
+        self.set_paused(train, true)
+    }
+
+    pub fn resume(&self, train: usize) -> Result<(), Error> {
+        self.set_paused(train, false)
+    }
+
+    pub fn pause_all(&self) {
+        for train in 0..self.trains.len() {
+            let _ = self.pause(train);
+        }
+    }
+
+    pub fn resume_all(&self) {
+        for train in 0..self.trains.len() {
+            let _ = self.resume(train);
+        }
+    }
+
+    pub fn is_paused(&self, train: usize) -> bool {
+        self.paused
+            .get(train)
+            .is_some_and(|paused| paused.load(Ordering::Relaxed))
+    }
+
+    fn set_paused(&self, train: usize, paused: bool) -> Result<(), Error> {
+        self.paused
+            .get(train)
+            .ok_or(Error::UnknownTrain(train))?
+            .store(paused, Ordering::Relaxed);
+        self.interlocking.set_paused(train as u64, paused);
+        if let Some(sender) = self.controls.lock_recover().get(train) {
+            let command = if paused {
+                Command::Pause
+            } else {
+                Command::Resume
+            };
+            // a thread pode já ter terminado; não há o que avisar
+            let _ = sender.send(command);
+        }
+        Ok(())
+    }
+
+    /// Aplica `change` ao trem e registra a nova velocidade se ela mudou.
+    fn change_velocity<F>(&self, train: usize, change: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Train) -> Result<(), Error>,
+    {
+        let mut train = self
+            .trains
+            .get(train)
+            .ok_or(Error::UnknownTrain(train))?
+            .lock_recover();
+        let before = train.velocity;
+        change(&mut train)?;
+
+        if train.velocity != before {
+            self.events
+                .record(train.id, EventKind::VelocityChanged(train.velocity));
+        }
+        Ok(())
+    }
+
+    /// Acompanha os trens, que outro já pôs para andar, por `duration` de
+    /// tempo simulado, chamando `on_enter` com o instante, o trem e o trilho
+    /// sempre que um trem muda de trilho. Devolve os deadlocks do fim.
+    pub(crate) fn watch<F>(&self, duration: Duration, mut on_enter: F) -> Vec<Deadlock>
+    where
+        F: FnMut(Duration, usize, &Track),
+    {
+        let start = self.events.elapsed();
+        let mut current: Vec<Option<usize>> = vec![None; self.positions.len()];
+        while self.events.elapsed() - start < duration {
+            for (train, position) in self.positions.iter().enumerate() {
+                if !self.is_enabled(train) {
+                    continue;
+                }
+                let track = position.lock_recover().track;
+                if current[train] != Some(track) {
+                    current[train] = Some(track);
+                    on_enter(self.events.elapsed() - start, train, &self.tracks[track]);
+                }
+            }
+            thread::sleep(POLL);
+        }
+        self.tracker.deadlocks()
+    }
+}
+
+/// Falha se não há um `setups` por circuito ou se algum trem começa num
+/// passo que a rota não tem.
+pub(crate) fn check_setups(layout: &Layout, setups: &[TrainSetup]) -> Result<(), Error> {
+    if setups.len() != layout.circuits.len() {
+        return Err(Error::WrongTrainCount {
+            expected: layout.circuits.len(),
+            found: setups.len(),
+        });
+    }
+    for (circuit, setup) in layout.circuits.iter().zip(setups) {
+        if setup.start_step >= circuit.route.len() {
+            return Err(Error::UnknownStep {
+                circuit: circuit.name.clone(),
+                step: setup.start_step,
+            });
+        }
+    }
+    Ok(())
+}
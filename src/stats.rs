@@ -0,0 +1,263 @@
+use crate::{circuit::Layout, Event, EventKind};
+use std::{collections::HashMap, fmt, time::Duration};
+
+#[derive(Clone, Debug, Default)]
+pub struct TrainStats {
+    pub laps: u64,
+    /// Soma da duração das voltas completas.
+    pub lap_time: Duration,
+    /// Tempo total esperando por trilhos.
+    pub blocked: Duration,
+}
+
+impl TrainStats {
+    pub fn average_lap(&self) -> Option<Duration> {
+        if self.laps == 0 {
+            return None;
+        }
+        Some(self.lap_time / self.laps as u32)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SegmentStats {
+    pub name: String,
+    pub shared: bool,
+    /// Tempo somado de todos os trens esperando por este trilho.
+    pub blocked: Duration,
+    /// Tempo em que o trilho ficou travado por algum trem.
+    pub busy: Duration,
+}
+
+/// Métricas calculadas a partir dos [`Event`]s de uma simulação.
+#[derive(Clone, Debug)]
+pub struct Statistics {
+    pub trains: Vec<TrainStats>,
+    pub segments: Vec<SegmentStats>,
+    first_tracks: Vec<usize>,
+    lap_starts: Vec<Option<Duration>>,
+    waiting: HashMap<u64, (usize, Duration)>,
+    holding: HashMap<usize, Duration>,
+}
+
+impl Statistics {
+    pub fn new(layout: &Layout) -> Statistics {
+        let circuits = layout.circuits.len();
+        Statistics {
+            trains: vec![TrainStats::default(); circuits],
+            segments: layout
+                .segments
+                .iter()
+                .map(|segment| SegmentStats {
+                    name: segment.name.clone(),
+                    shared: segment.shared,
+                    ..Default::default()
+                })
+                .collect(),
+            first_tracks: (0..circuits).map(|i| layout.route(i)[0][0]).collect(),
+            lap_starts: vec![None; circuits],
+            waiting: HashMap::new(),
+            holding: HashMap::new(),
+        }
+    }
+
+    pub fn from_events(layout: &Layout, events: &[Event]) -> Statistics {
+        let mut statistics = Statistics::new(layout);
+        for event in events {
+            statistics.record(event);
+        }
+        statistics
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        let train = event.train as usize;
+        match event.kind {
+            EventKind::Entered(segment) if segment == self.first_tracks[train] => {
+                if let Some(start) = self.lap_starts[train] {
+                    self.trains[train].laps += 1;
+                    self.trains[train].lap_time += event.time - start;
+                }
+                self.lap_starts[train] = Some(event.time);
+            }
+            EventKind::Requested(segment) => {
+                self.waiting.insert(event.train, (segment, event.time));
+            }
+            EventKind::Acquired(segment) => {
+                if let Some((_, since)) = self.waiting.remove(&event.train) {
+                    self.trains[train].blocked += event.time - since;
+                    self.segments[segment].blocked += event.time - since;
+                }
+                self.holding.insert(segment, event.time);
+            }
+            EventKind::Released(segment) => {
+                if let Some(since) = self.holding.remove(&segment) {
+                    self.segments[segment].busy += event.time - since;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Tempo bloqueado do trem até `now`, contando a espera em andamento.
+    pub fn train_blocked(&self, train: usize, now: Duration) -> Duration {
+        let waiting = self
+            .waiting
+            .get(&(train as u64))
+            .map(|(_, since)| now.saturating_sub(*since))
+            .unwrap_or_default();
+        self.trains[train].blocked + waiting
+    }
+
+    /// Tempo de espera pelo trilho até `now`, contando as esperas em andamento.
+    pub fn segment_blocked(&self, segment: usize, now: Duration) -> Duration {
+        let waiting: Duration = self
+            .waiting
+            .values()
+            .filter(|(waited, _)| *waited == segment)
+            .map(|(_, since)| now.saturating_sub(*since))
+            .sum();
+        self.segments[segment].blocked + waiting
+    }
+
+    /// Fração do tempo até `now` em que o trilho esteve travado.
+    pub fn utilization(&self, segment: usize, now: Duration) -> f64 {
+        if now.is_zero() {
+            return 0.0;
+        }
+        let holding = self
+            .holding
+            .get(&segment)
+            .map(|since| now.saturating_sub(*since))
+            .unwrap_or_default();
+        (self.segments[segment].busy + holding).as_secs_f64() / now.as_secs_f64()
+    }
+
+    /// Voltas completas de todos os trens por minuto.
+    pub fn throughput(&self, now: Duration) -> f64 {
+        if now.is_zero() {
+            return 0.0;
+        }
+        let laps: u64 = self.trains.iter().map(|train| train.laps).sum();
+        laps as f64 * 60.0 / now.as_secs_f64()
+    }
+
+    pub fn report(&self, now: Duration) -> Report<'_> {
+        Report {
+            statistics: self,
+            now,
+        }
+    }
+}
+
+/// Resumo em texto das estatísticas até um instante.
+pub struct Report<'a> {
+    statistics: &'a Statistics,
+    now: Duration,
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let statistics = self.statistics;
+        for (i, train) in statistics.trains.iter().enumerate() {
+            let average = train
+                .average_lap()
+                .map(|lap| format!("{:.1}s", lap.as_secs_f64()))
+                .unwrap_or_else(|| "-".to_string());
+            writeln!(
+                f,
+                "train {}: {} laps, {} per lap, {:.1}s blocked",
+                i,
+                train.laps,
+                average,
+                statistics.train_blocked(i, self.now).as_secs_f64()
+            )?;
+        }
+        for (i, segment) in statistics.segments.iter().enumerate() {
+            writeln!(
+                f,
+                "{}: {:.0}% busy, {:.1}s waited",
+                segment.name,
+                100.0 * statistics.utilization(i, self.now),
+                statistics.segment_blocked(i, self.now).as_secs_f64()
+            )?;
+        }
+        write!(
+            f,
+            "throughput: {:.2} laps/min",
+            statistics.throughput(self.now)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // N1, N2, N3, C (o compartilhado), S1, S2 e S3
+    const N1: usize = 0;
+    const N2: usize = 1;
+    const C: usize = 3;
+    const S1: usize = 4;
+    const S2: usize = 5;
+
+    fn statistics() -> Statistics {
+        let layout = Layout::from_ron(include_str!("../assets/layouts/two_trains.ron")).unwrap();
+        let event = |seconds: u64, train: u64, kind: EventKind| Event {
+            time: Duration::from_secs(seconds),
+            train,
+            kind,
+        };
+        let events = [
+            event(0, 0, EventKind::Entered(N1)),
+            event(0, 1, EventKind::Entered(S1)),
+            event(10, 0, EventKind::Requested(C)),
+            event(10, 0, EventKind::Acquired(C)),
+            event(10, 0, EventKind::Entered(C)),
+            event(12, 1, EventKind::Requested(C)),
+            event(15, 0, EventKind::Released(C)),
+            event(15, 1, EventKind::Acquired(C)),
+            event(20, 0, EventKind::Entered(N1)),
+            event(25, 1, EventKind::Released(C)),
+            event(26, 1, EventKind::Requested(S2)),
+            event(27, 0, EventKind::Requested(N2)),
+            event(27, 0, EventKind::Acquired(N2)),
+            event(28, 0, EventKind::Entered(N1)),
+        ];
+        Statistics::from_events(&layout, &events)
+    }
+
+    #[test]
+    fn laps_count_returns_to_the_first_track() {
+        let statistics = statistics();
+        assert_eq!(statistics.trains[0].laps, 2);
+        assert_eq!(
+            statistics.trains[0].average_lap(),
+            Some(Duration::from_secs(14))
+        );
+        assert_eq!(statistics.trains[1].laps, 0);
+        assert_eq!(statistics.trains[1].average_lap(), None);
+        assert_eq!(statistics.throughput(Duration::from_secs(30)), 4.0);
+    }
+
+    #[test]
+    fn blocked_time_runs_from_request_to_grant() {
+        let statistics = statistics();
+        let now = Duration::from_secs(30);
+        assert_eq!(statistics.train_blocked(0, now), Duration::ZERO);
+        // 3s esperando C e a espera por S2, ainda em andamento
+        assert_eq!(statistics.train_blocked(1, now), Duration::from_secs(7));
+        assert_eq!(statistics.segment_blocked(C, now), Duration::from_secs(3));
+        assert_eq!(statistics.segment_blocked(S2, now), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn busy_time_runs_from_grant_to_release() {
+        let statistics = statistics();
+        let now = Duration::from_secs(30);
+        assert_eq!(statistics.segments[C].busy, Duration::from_secs(15));
+        assert_eq!(statistics.utilization(C, now), 0.5);
+        // N2 continua travado desde 27s
+        assert_eq!(statistics.utilization(N2, now), 0.1);
+        assert_eq!(statistics.utilization(C, Duration::ZERO), 0.0);
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use tracks::{Simulation, Statistics};
+
+use crate::StatsText;
+
+pub struct StatsPlugin;
+
+/// Quantos eventos do registro já foram contabilizados.
+struct StatsCursor(usize);
+
+struct StatsRefresh(Timer);
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(stats_setup)
+            .add_system(stats_update)
+            .add_system(stats_text_update);
+    }
+}
+
+fn stats_setup(mut commands: Commands, simulation: Res<Simulation>) {
+    commands.insert_resource(Statistics::new(&simulation.layout));
+    commands.insert_resource(StatsCursor(0));
+    commands.insert_resource(StatsRefresh(Timer::from_seconds(0.5, true)));
+}
+
+fn stats_update(
+    simulation: Res<Simulation>,
+    mut statistics: ResMut<Statistics>,
+    mut cursor: ResMut<StatsCursor>,
+) {
+    // a simulação foi reiniciada: o registro de eventos é outro
+    if simulation.is_changed() {
+        *statistics = Statistics::new(&simulation.layout);
+        cursor.0 = 0;
+    }
+
+    let events = simulation.events.events_since(cursor.0);
+    cursor.0 += events.len();
+
+    for event in &events {
+        statistics.record(event);
+    }
+}
+
+fn stats_text_update(
+    mut query: Query<&mut Text, With<StatsText>>,
+    mut refresh: ResMut<StatsRefresh>,
+    time: Res<Time>,
+    simulation: Res<Simulation>,
+    statistics: Res<Statistics>,
+) {
+    if !refresh.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let now = simulation.events.elapsed();
+    let mut lines = Vec::new();
+    for (i, (train, circuit)) in statistics
+        .trains
+        .iter()
+        .zip(&simulation.layout.circuits)
+        .enumerate()
+    {
+        let average = train
+            .average_lap()
+            .map(|lap| format!("{:.1}s", lap.as_secs_f64()))
+            .unwrap_or_else(|| "-".to_string());
+        lines.push(format!(
+            "{}: {} voltas, {}/volta, {:.1}s parado",
+            circuit.name,
+            train.laps,
+            average,
+            statistics.train_blocked(i, now).as_secs_f64()
+        ));
+    }
+
+    for (i, segment) in statistics.segments.iter().enumerate() {
+        let mut line = format!(
+            "{}: {:.0}% uso",
+            segment.name,
+            100.0 * statistics.utilization(i, now)
+        );
+        if segment.shared {
+            let blocked = statistics.segment_blocked(i, now);
+            line += &format!(", {:.1}s espera", blocked.as_secs_f64());
+        }
+        lines.push(line);
+    }
+    lines.push(format!(
+        "vazão: {:.2} voltas/min",
+        statistics.throughput(now)
+    ));
+
+    let value = lines.join("\n");
+    query.for_each_mut(|mut text| {
+        text.sections[0].value = value.clone();
+    });
+}
@@ -0,0 +1,154 @@
+use crate::{
+    train_machine::{Network, TrainMachine},
+    Event,
+};
+use std::{
+    borrow::{Borrow, BorrowMut},
+    time::Duration,
+};
+
+/// Por que um trem não anda no passo a passo.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Blocked {
+    /// Esperando um trilho que está com outro trem.
+    Held {
+        segment: usize,
+        holder: u64,
+    },
+    /// O trilho está livre, mas a estratégia ainda não deixa o trem entrar.
+    Refused {
+        segment: usize,
+    },
+    /// O trilho está livre, mas pela política de entrega é a vez de `next`.
+    Behind {
+        segment: usize,
+        next: u64,
+    },
+    /// O trilho acabou de ficar livre; o trem o obtém no próximo passo.
+    Ready {
+        segment: usize,
+    },
+    Paused,
+}
+
+/// O que aconteceu em um passo.
+#[derive(Clone, Debug)]
+pub struct Step {
+    /// Trem que agiu.
+    pub train: usize,
+    /// Tempo simulado ao fim do passo.
+    pub time: Duration,
+    /// Eventos registrados no passo, na ordem.
+    pub events: Vec<Event>,
+    /// Trens que não podem andar agora e o motivo, em ordem de trem.
+    pub blocked: Vec<(usize, Blocked)>,
+}
+
+/// Avança as máquinas até o próximo acontecimento: o primeiro trem (em ordem
+/// de trem) que pode obter o trilho que esperava, que obtém só esse, ou, se
+/// nenhum pode, o trem que termina antes o trilho em que está, que o libera
+/// e para aí; o próximo trilho fica para um passo seguinte. Os outros trens
+/// andam o mesmo tempo, mas sem sair do trilho: quem empatar age depois.
+/// Devolve `None` quando nenhum trem pode agir, por exemplo em
+/// um deadlock ou com todos pausados.
+///
+/// O relógio de `network.events` deve estar parado ([`crate::Clock::pause`]):
+/// o passo o adianta do tempo que os trens andaram.
+pub fn step<M, P>(machines: &mut [M], network: &Network, is_paused: P) -> Option<Step>
+where
+    M: BorrowMut<TrainMachine>,
+    P: Fn(usize) -> bool,
+{
+    let from = network.events.count();
+    let train = grant_waiting(machines, network, &is_paused)
+        .or_else(|| finish_track(machines, network, &is_paused))?;
+
+    Some(Step {
+        train,
+        time: network.events.elapsed(),
+        events: network.events.events_since(from),
+        blocked: blocked(machines, network, &is_paused),
+    })
+}
+
+/// Trens que não podem andar agora e o motivo, em ordem de trem.
+pub fn blocked<M, P>(machines: &[M], network: &Network, is_paused: P) -> Vec<(usize, Blocked)>
+where
+    M: Borrow<TrainMachine>,
+    P: Fn(usize) -> bool,
+{
+    let mut blocked: Vec<(usize, Blocked)> = machines
+        .iter()
+        .map(Borrow::borrow)
+        .filter_map(|machine| {
+            let train = machine.train();
+            if is_paused(train) {
+                return Some((train, Blocked::Paused));
+            }
+            let segment = machine.waiting_for()?;
+            let interlocking = network.interlocking;
+            let reason = match interlocking.holder(segment) {
+                Some(holder) => Blocked::Held { segment, holder },
+                None if interlocking.can_acquire(train as u64, segment) => {
+                    Blocked::Ready { segment }
+                }
+                None => match interlocking.next_in_line(segment) {
+                    Some(next) if next != train as u64 => Blocked::Behind { segment, next },
+                    _ => Blocked::Refused { segment },
+                },
+            };
+            Some((train, reason))
+        })
+        .collect();
+    blocked.sort_by_key(|(train, _)| *train);
+    blocked
+}
+
+/// Dá a vez ao primeiro trem parado que consegue obter o trilho que espera.
+fn grant_waiting<M, P>(machines: &mut [M], network: &Network, is_paused: &P) -> Option<usize>
+where
+    M: BorrowMut<TrainMachine>,
+    P: Fn(usize) -> bool,
+{
+    let mut waiting: Vec<&mut TrainMachine> = machines
+        .iter_mut()
+        .map(BorrowMut::borrow_mut)
+        .filter(|machine| !machine.is_running() && !is_paused(machine.train()))
+        .collect();
+    waiting.sort_by_key(|machine| machine.train());
+
+    waiting
+        .into_iter()
+        .find_map(|machine| machine.acquire_next(network).then_some(machine.train()))
+}
+
+/// Anda com todos os trens até o primeiro deles terminar o trilho.
+fn finish_track<M, P>(machines: &mut [M], network: &Network, is_paused: &P) -> Option<usize>
+where
+    M: BorrowMut<TrainMachine>,
+    P: Fn(usize) -> bool,
+{
+    let mut running: Vec<&mut TrainMachine> = machines
+        .iter_mut()
+        .map(BorrowMut::borrow_mut)
+        .filter(|machine| machine.is_running() && !is_paused(machine.train()))
+        .collect();
+    running.sort_by_key(|machine| machine.train());
+
+    let (train, elapsed) = running
+        .iter()
+        .filter_map(|machine| Some((machine.train(), machine.remaining(network)?)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+    network
+        .events
+        .clock()
+        .advance(Duration::from_secs_f64(elapsed));
+    for machine in running {
+        machine.travel(elapsed, network);
+        if machine.train() == train {
+            machine.leave(network);
+        }
+    }
+    Some(train)
+}
@@ -1,19 +1,31 @@
 use bevy::prelude::*;
+use tracks::Simulation;
 
 use crate::UiTrackPos;
 
 pub struct TextPlugin;
 
+#[derive(Component)]
+struct DeadlockText;
+
+#[derive(Component)]
+struct TitleText;
+
+/// Nome do trilho na posição `ui_tracks.track_pos[i]`.
+#[derive(Component)]
+struct TrackLabel(usize);
+
 impl Plugin for TextPlugin {
     fn build(&self, app: &mut App) {
         app.add_startup_stage(
             "spawn_text_entities",
             SystemStage::single(spawn_text_entities),
-        );
+        )
+        .add_system(deadlock_text_update)
+        .add_system(text_relayout);
     }
 }
 
-
 fn spawn_text_entities(
     mut commands: Commands,
     ui_tracks: Res<UiTrackPos>,
@@ -23,18 +35,42 @@ fn spawn_text_entities(
 
     let track_pos = &ui_tracks.track_pos;
 
-    for (pos, text_value) in track_pos {
-         let translation = Vec3::new(pos.x,pos.y,200.0);
-        commands.spawn_bundle(Text2dBundle {
+    for (index, (pos, text_value)) in track_pos.iter().enumerate() {
+        let translation = Vec3::new(pos.x, pos.y, 200.0);
+        commands
+            .spawn_bundle(Text2dBundle {
+                transform: Transform {
+                    translation: translation,
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    text_value.clone(),
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: ui_tracks.label_size,
+                        color: Color::BLACK,
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                ..Default::default()
+            })
+            .insert(TrackLabel(index));
+    }
+
+    commands
+        .spawn_bundle(Text2dBundle {
             transform: Transform {
-                translation: translation,
+                translation: ui_tracks.title_pos.extend(110.0),
                 ..Default::default()
             },
             text: Text::with_section(
-                text_value.clone(),
+                "Visualização dinâmica dos trens",
                 TextStyle {
                     font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                    font_size: 45.0,
+                    font_size: 60.0,
                     color: Color::BLACK,
                 },
                 TextAlignment {
@@ -43,30 +79,82 @@ fn spawn_text_entities(
                 },
             ),
             ..Default::default()
-        });
-    }
+        })
+        .insert(TitleText);
 
-    commands.spawn_bundle(Text2dBundle {
-        transform: Transform {
-            translation: Vec3::new(
-                ui_tracks.center_blue_track.x,
-                -ui_tracks.center_blue_track.y + 150.0,
-                110.0,
+    commands
+        .spawn_bundle(Text2dBundle {
+            transform: Transform {
+                translation: Vec3::new(ui_tracks.title_pos.x, ui_tracks.title_pos.y - 60.0, 110.0),
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 25.0,
+                    color: Color::RED,
+                },
+                TextAlignment {
+                    vertical: VerticalAlign::Center,
+                    horizontal: HorizontalAlign::Center,
+                },
             ),
             ..Default::default()
-        },
-        text: Text::with_section(
-            "Visualização dinâmica dos trens",
-            TextStyle {
-                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                font_size: 60.0,
-                color: Color::BLACK,
-            },
-            TextAlignment {
-                vertical: VerticalAlign::Center,
-                horizontal: HorizontalAlign::Center,
-            },
-        ),
-        ..Default::default()
+        })
+        .insert(DeadlockText);
+}
+
+fn deadlock_text_update(
+    mut query: Query<&mut Text, With<DeadlockText>>,
+    simulation: Res<Simulation>,
+) {
+    let message = simulation
+        .tracker
+        .deadlocks()
+        .iter()
+        .map(|deadlock| {
+            let segments = deadlock.segments.join(", ");
+            format!("Deadlock: trens {:?} em {}", deadlock.trains, segments)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    query.for_each_mut(|mut text| {
+        if text.sections[0].value != message {
+            text.sections[0].value = message.clone();
+        }
+    });
+}
+
+type DeadlockTransform<'w, 's> = Query<
+    'w,
+    's,
+    &'static mut Transform,
+    (With<DeadlockText>, Without<TrackLabel>, Without<TitleText>),
+>;
+
+/// Acompanha o [`UiTrackPos`] recalculado quando a janela muda de tamanho.
+fn text_relayout(
+    ui_tracks: Res<UiTrackPos>,
+    mut labels: Query<(&TrackLabel, &mut Transform, &mut Text)>,
+    mut title: Query<&mut Transform, (With<TitleText>, Without<TrackLabel>)>,
+    mut deadlock: DeadlockTransform,
+) {
+    if !ui_tracks.is_changed() {
+        return;
+    }
+
+    labels.for_each_mut(|(label, mut transform, mut text)| {
+        let (pos, _) = &ui_tracks.track_pos[label.0];
+        transform.translation = Vec3::new(pos.x, pos.y, transform.translation.z);
+        text.sections[0].style.font_size = ui_tracks.label_size;
+    });
+    title.for_each_mut(|mut transform| {
+        transform.translation = ui_tracks.title_pos.extend(transform.translation.z);
+    });
+    deadlock.for_each_mut(|mut transform| {
+        let pos = ui_tracks.title_pos - Vec2::new(0.0, 60.0);
+        transform.translation = pos.extend(transform.translation.z);
     });
 }
@@ -1,42 +1,72 @@
-use crate::Train;
-use std::time::Duration;
+use crate::{Clock, Error, LockRecover, Stopped, Train, TrainControl};
+use std::sync::Mutex;
 use std::thread::sleep;
+use std::time::Duration;
+
+/// Intervalo em que o trem relê a sua velocidade enquanto percorre o trilho.
+pub(crate) const TICK: Duration = Duration::from_millis(10);
 
 pub struct Track {
     pub name: String,
-    pub distance: u64,
+    pub distance: f64,
 }
 
+/// Onde o trem está: o índice do trilho atual na malha e a fração dele já
+/// percorrida (0 a 1).
 #[derive(Clone)]
-pub enum TrackState {
-    L1 = 0,
-    L2,
-    L3,
-    L4,
-    L5,
-    L6,
-    L7,
-    L8,
-    L9,
-    L10,
-    L11,
-    L12,
-    L13,
+pub struct TrainPosition {
+    pub track: usize,
+    pub progress: f64,
+}
+
+impl TrainPosition {
+    pub fn new(track: usize) -> TrainPosition {
+        TrainPosition {
+            track,
+            progress: 0.0,
+        }
+    }
 }
 
 impl Track {
-    pub fn new(name: String, distance: u64) -> Track {
-        Track {
-            name,
-            distance,
+    /// A distância precisa ser finita e positiva.
+    pub fn new(name: String, distance: f64) -> Result<Track, Error> {
+        if !(distance > 0.0 && distance.is_finite()) {
+            return Err(Error::InvalidDistance {
+                track: name,
+                distance,
+            });
         }
+        Ok(Track { name, distance })
     }
 
-    pub fn run(&self, train: &Train) {
-        // println!("train id: {} vel: {} in {}", train.id, train.velocity, self.name);
+    pub fn run(
+        &self,
+        train: &Mutex<Train>,
+        position: &Mutex<TrainPosition>,
+        clock: &Clock,
+        control: &mut TrainControl,
+    ) -> Result<(), Stopped> {
         // v = d/t
-        // t = d/v
-        let time = self.distance / train.velocity;
-        sleep(Duration::new(time, 0));
+        // t = d/v, recalculado a cada TICK para acompanhar mudanças de velocidade
+        // e de escala; a distância anda com o tempo simulado do relógio
+        let mut travelled = 0.0;
+        while travelled < self.distance {
+            control.checkpoint()?;
+
+            let velocity = train.lock_recover().velocity;
+            if velocity <= 0.0 {
+                sleep(TICK);
+                continue;
+            }
+
+            let remaining = (self.distance - travelled) / velocity / clock.scale();
+            let start = clock.now();
+            sleep(Duration::from_secs_f64(remaining).min(TICK));
+            travelled += velocity * clock.now().saturating_sub(start).as_secs_f64();
+
+            position.lock_recover().progress = (travelled / self.distance).min(1.0);
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,195 @@
+use crate::{
+    circuit::{Interlocking, Layout},
+    track::TrainPosition,
+    EventKind, EventLog, LockRecover, Track, Train, TrainSetup,
+};
+use std::sync::{Arc, Mutex};
+
+/// Trilhos, trens e intertravamento que as máquinas de estado consultam e
+/// alteram, emprestados de quem os montou.
+pub struct Network<'a> {
+    pub tracks: &'a [Arc<Track>],
+    pub interlocking: &'a Interlocking,
+    pub events: &'a EventLog,
+    pub trains: &'a [Arc<Mutex<Train>>],
+    pub positions: &'a [Arc<Mutex<TrainPosition>>],
+}
+
+/// Em que ponto do passo atual da rota o trem está.
+enum Stage {
+    /// Vai declarar a região crítica do passo.
+    Entering,
+    /// Esperando o trilho `order[next]` do passo.
+    Acquiring { order: Vec<usize>, next: usize },
+    /// Percorrendo o trilho `index` do passo.
+    Running { index: usize, travelled: f64 },
+}
+
+/// Trem como máquina de estados: [`TrainMachine::advance`] anda o que der sem
+/// bloquear e devolve o controle, seguindo as mesmas regras de
+/// [`crate::circuit::LayoutCircuit`]. Quem chama decide quando e com que
+/// intervalo, seja um pool de threads ou um sistema do Bevy.
+pub struct TrainMachine {
+    train: usize,
+    route: Vec<Vec<usize>>,
+    step: usize,
+    stage: Stage,
+}
+
+impl TrainMachine {
+    /// O trem `train` percorre `route` a partir do primeiro passo.
+    pub fn new(train: usize, route: Vec<Vec<usize>>) -> TrainMachine {
+        TrainMachine {
+            train,
+            route,
+            step: 0,
+            stage: Stage::Entering,
+        }
+    }
+
+    /// Uma máquina para cada trem ligado, com a rota girada para o passo
+    /// inicial; `setups` já precisa ter sido conferido contra a malha.
+    pub fn from_setups(layout: &Layout, setups: &[TrainSetup]) -> Vec<TrainMachine> {
+        setups
+            .iter()
+            .enumerate()
+            .filter(|(_, setup)| setup.enabled)
+            .map(|(train, setup)| {
+                let mut route = layout.route(train);
+                route.rotate_left(setup.start_step);
+                TrainMachine::new(train, route)
+            })
+            .collect()
+    }
+
+    pub fn train(&self) -> usize {
+        self.train
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.stage, Stage::Running { .. })
+    }
+
+    /// Trilho que o trem pediu e ainda não obteve.
+    pub fn waiting_for(&self) -> Option<usize> {
+        match &self.stage {
+            Stage::Acquiring { order, next } => Some(order[*next]),
+            _ => None,
+        }
+    }
+
+    /// Segundos até o trem terminar o trilho em que está, na velocidade
+    /// atual; `None` se ele não está andando.
+    pub fn remaining(&self, network: &Network) -> Option<f64> {
+        let Stage::Running { index, travelled } = self.stage else {
+            return None;
+        };
+        let distance = network.tracks[self.route[self.step][index]].distance;
+        let velocity = network.trains[self.train].lock_recover().velocity;
+        (velocity > 0.0).then(|| (distance - travelled) / velocity)
+    }
+
+    /// Avança `elapsed` segundos. O tempo passado esperando um trilho não
+    /// vira distância: só anda quem já estava percorrendo um trilho.
+    pub fn advance(&mut self, elapsed: f64, network: &Network) {
+        let mut budget = match self.stage {
+            Stage::Running { .. } => elapsed,
+            _ => 0.0,
+        };
+        loop {
+            let Stage::Running { index, travelled } = &mut self.stage else {
+                if !self.acquire_next(network) {
+                    return;
+                }
+                continue;
+            };
+            let distance = network.tracks[self.route[self.step][*index]].distance;
+            let velocity = network.trains[self.train].lock_recover().velocity;
+
+            let needed = if velocity > 0.0 {
+                (distance - *travelled) / velocity
+            } else {
+                f64::INFINITY
+            };
+            if budget < needed {
+                *travelled += velocity * budget;
+                network.positions[self.train].lock_recover().progress =
+                    (*travelled / distance).min(1.0);
+                return;
+            }
+            budget -= needed;
+            self.leave(network);
+        }
+    }
+
+    /// Tenta obter só o próximo trilho que o trem espera, declarando antes a
+    /// região crítica se ele chegou a um passo novo; ao obter o último trilho
+    /// da região, entra no primeiro. Devolve se obteve um trilho.
+    pub fn acquire_next(&mut self, network: &Network) -> bool {
+        let id = self.train as u64;
+        if let Stage::Entering = self.stage {
+            let order = network.interlocking.enter(id, &self.route[self.step]);
+            network.interlocking.request(id, order[0]);
+            self.stage = Stage::Acquiring { order, next: 0 };
+        }
+        let Stage::Acquiring { order, next } = &mut self.stage else {
+            return false;
+        };
+        if !network.interlocking.try_acquire(id, order[*next]) {
+            return false;
+        }
+        *next += 1;
+        match order.get(*next).copied() {
+            Some(segment) => network.interlocking.request(id, segment),
+            None => self.enter_track(0, network),
+        }
+        true
+    }
+
+    /// Sai do trilho em que está, como se já o tivesse percorrido: libera o
+    /// trilho e entra no próximo da região crítica, que já é do trem, ou
+    /// passa ao próximo passo da rota sem pedir nada ainda.
+    pub fn leave(&mut self, network: &Network) {
+        let Stage::Running { index, .. } = self.stage else {
+            return;
+        };
+        let segment = self.route[self.step][index];
+        network.interlocking.release(self.train as u64, segment);
+        if index + 1 < self.route[self.step].len() {
+            self.enter_track(index + 1, network);
+        } else {
+            self.step = (self.step + 1) % self.route.len();
+            self.stage = Stage::Entering;
+        }
+    }
+
+    /// Anda `elapsed` segundos no trilho atual, parando no fim dele em vez de
+    /// passar para o próximo; só faz algo se o trem está andando.
+    pub fn travel(&mut self, elapsed: f64, network: &Network) {
+        let Stage::Running { index, travelled } = &mut self.stage else {
+            return;
+        };
+        let distance = network.tracks[self.route[self.step][*index]].distance;
+        let velocity = network.trains[self.train].lock_recover().velocity;
+        *travelled = (*travelled + velocity * elapsed).min(distance);
+        network.positions[self.train].lock_recover().progress = *travelled / distance;
+    }
+
+    /// Libera os trilhos do trem; na próxima vez ele recomeça o passo atual.
+    pub fn reset(&mut self, network: &Network) {
+        network.interlocking.release_all(self.train as u64);
+        self.stage = Stage::Entering;
+    }
+
+    fn enter_track(&mut self, index: usize, network: &Network) {
+        let segment = self.route[self.step][index];
+        *network.positions[self.train].lock_recover() = TrainPosition::new(segment);
+        network
+            .events
+            .record(self.train as u64, EventKind::Entered(segment));
+        self.stage = Stage::Running {
+            index,
+            travelled: 0.0,
+        };
+    }
+}
@@ -1,19 +1,8 @@
-use std::{
-    sync::{Arc, Mutex},
-    thread::JoinHandle,
-};
-
-use bevy::prelude::*;
-use tracks::{
-    circuit::{BlueCircuit, Circuit, GreenCircuit, PurpleCircuit, RedCircuit},
-    Track, Train,
-};
+use bevy::{app::AppExit, prelude::*};
+use tracks::{Executor, Simulation};
 
 use crate::{TrainMaterials, TrainState, UiTrackPos};
 
-#[derive(Component)]
-struct ThreadComponent(JoinHandle<()>);
-
 pub struct TrainPlugin;
 
 impl Plugin for TrainPlugin {
@@ -22,7 +11,9 @@ impl Plugin for TrainPlugin {
             "spawn_train_entities",
             SystemStage::single(train_block_spawn),
         )
-        .add_system(train_update);
+        .add_system(train_update)
+        .add_system(train_resize)
+        .add_system_to_stage(CoreStage::Last, stop_on_exit);
     }
 }
 
@@ -31,49 +22,25 @@ fn train_block_spawn(
     materials: Res<Assets<ColorMaterial>>,
     train_materials: Res<TrainMaterials>,
     ui_tracks: Res<UiTrackPos>,
-    tracks: Res<Vec<Arc<Mutex<Track>>>>,
+    simulation: Res<Simulation>,
 ) {
     println!("train_block_spawn!!");
 
-    let size = Vec2::new(
-        train_materials.train_block_size,
-        train_materials.train_block_size,
-    );
-
-    let green_circuit = GreenCircuit::new(tracks.clone());
-    let purple_circuit = PurpleCircuit::new(tracks.clone());
-    let red_circuit = RedCircuit::new(tracks.clone());
-    let blue_circuit = BlueCircuit::new(tracks.clone());
-
-    let circuits: Vec<Arc<dyn Circuit + Send + Sync>> = vec![
-        Arc::new(green_circuit),
-        Arc::new(purple_circuit),
-        Arc::new(red_circuit),
-        Arc::new(blue_circuit),
-    ];
-
-    let trains = vec![
-        Arc::new(Mutex::new(Train::new(0, 2))),
-        Arc::new(Mutex::new(Train::new(1, 3))),
-        Arc::new(Mutex::new(Train::new(2, 3))),
-        Arc::new(Mutex::new(Train::new(3, 4))),
-    ];
+    let size = Vec2::splat(ui_tracks.train_size);
 
-    let train_materials = vec![
-        train_materials.green_train_material.clone(),
-        train_materials.purple_train_material.clone(),
-        train_materials.red_train_material.clone(),
-        train_materials.blue_train_material.clone(),
-    ];
-    commands.insert_resource(trains.clone());
+    simulation.spawn();
 
-    for ((circuit, train), material) in circuits.iter().zip(trains).zip(train_materials) {
-        let state = Arc::new(Mutex::new(circuit.initial_track_state()));
-        let translation = ui_tracks.track_pos[circuit.initial_track_state() as usize]
-            .0
-            .clone();
+    for (index, (circuit, material)) in simulation
+        .circuits
+        .iter()
+        .zip(&train_materials.materials)
+        .enumerate()
+    {
+        let translation = ui_tracks.loops[index]
+            .point_at(circuit.initial_track(), 0.0)
+            .unwrap_or_default()
+            .extend(100.0);
 
-        let cloned_circuit: Arc<dyn Circuit + Send + Sync> = circuit.clone();
         let color = materials.get(material.clone()).unwrap().color;
         commands
             .spawn_bundle(SpriteBundle {
@@ -89,25 +56,45 @@ fn train_block_spawn(
                 },
                 ..Default::default()
             })
-            .insert(TrainState {
-                state: state.clone(),
-            })
-            .insert(ThreadComponent(std::thread::spawn(move || loop {
-                let circuit_train;
-                {
-                    circuit_train = train.lock().unwrap().clone();
-                }
-
-                cloned_circuit.run(state.clone(), &circuit_train);
-            })));
+            .insert(TrainState { circuit: index });
     }
 }
 
-fn train_update(mut query: Query<(&TrainState, &mut Transform)>, ui_tracks: Res<UiTrackPos>) {
-    query.for_each_mut(|(train_state, mut transform)| {
-        if let Ok(mutex) = train_state.state.try_lock() {
-            let state = mutex.clone();
-            transform.translation = ui_tracks.track_pos[state as usize].0.clone();
+fn train_update(
+    mut query: Query<(&TrainState, &mut Transform, &mut Visibility)>,
+    ui_tracks: Res<UiTrackPos>,
+    simulation: Res<Simulation>,
+) {
+    query.for_each_mut(|(train_state, mut transform, mut visibility)| {
+        // trens desligados pelo cenário não aparecem
+        visibility.is_visible = simulation.is_enabled(train_state.circuit);
+        // sem threads, quem posiciona os trens é o modo ECS
+        if simulation.executor() == Executor::Stepped {
+            return;
+        }
+
+        if let Ok(mutex) = simulation.positions[train_state.circuit].try_lock() {
+            let track = mutex.track;
+            let path = &ui_tracks.loops[train_state.circuit];
+            if let Some(point) = path.point_at(track, mutex.progress as f32) {
+                transform.translation = point.extend(transform.translation.z);
+            }
         }
     });
 }
+
+/// Sem isso as threads dos trens continuariam rodando depois de a janela fechar.
+fn stop_on_exit(mut exit: EventReader<AppExit>, simulation: Res<Simulation>) {
+    if exit.iter().next().is_some() {
+        simulation.stop();
+    }
+}
+
+fn train_resize(ui_tracks: Res<UiTrackPos>, mut query: Query<&mut Sprite, With<TrainState>>) {
+    if !ui_tracks.is_changed() {
+        return;
+    }
+    query.for_each_mut(|mut sprite| {
+        sprite.custom_size = Some(Vec2::splat(ui_tracks.train_size));
+    });
+}
@@ -0,0 +1,395 @@
+use crate::{
+    circuit::{GrantPolicy, Layout, LockState, SyncKind, SyncStrategy},
+    simulation_core::check_setups,
+    Error, EventKind, EventLog, Track, Train, TrainSetup,
+};
+use std::{cmp::Reverse, collections::BinaryHeap, sync::Arc, time::Duration};
+
+/// Gerador pseudoaleatório SplitMix64, usado para desempatar concessões
+/// simultâneas de forma reprodutível.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+enum Phase {
+    /// Pedindo os trilhos do passo na ordem dada pela estratégia.
+    Requesting { order: Vec<usize>, next: usize },
+    /// Percorrendo o trilho `index` do passo; faltava `remaining` em `since`.
+    Traversing {
+        index: usize,
+        remaining: f64,
+        since: Duration,
+    },
+}
+
+struct VirtualTrain {
+    train: Train,
+    route: Vec<Vec<usize>>,
+    step: usize,
+    phase: Phase,
+    last_track: Option<usize>,
+    /// Invalida eventos já agendados quando a velocidade muda.
+    version: u64,
+}
+
+/// Simulação de eventos discretos com relógio virtual: os mesmos trilhos,
+/// rotas e estratégias da [`crate::Simulation`], sem threads nem `sleep`.
+///
+/// Para a mesma semente a sequência de eventos é sempre a mesma.
+pub struct VirtualSimulation {
+    pub tracks: Vec<Arc<Track>>,
+    pub events: EventLog,
+    strategy: Box<dyn SyncStrategy>,
+    policy: GrantPolicy,
+    locks: LockState,
+    trains: Vec<VirtualTrain>,
+    clock: Duration,
+    queue: BinaryHeap<Reverse<(Duration, u64, usize, u64)>>,
+    sequence: u64,
+    rng: SplitMix64,
+}
+
+impl VirtualSimulation {
+    pub fn new(
+        layout: &Layout,
+        sync: SyncKind,
+        velocities: &[f64],
+        seed: u64,
+    ) -> Result<Self, Error> {
+        let setups = TrainSetup::defaults(layout, velocities);
+        VirtualSimulation::with_setups(layout, sync, &setups, seed)
+    }
+
+    /// Falha nos mesmos casos que [`crate::Simulation::with_setups`].
+    pub fn with_setups(
+        layout: &Layout,
+        sync: SyncKind,
+        setups: &[TrainSetup],
+        seed: u64,
+    ) -> Result<Self, Error> {
+        check_setups(layout, setups)?;
+        let trains = setups
+            .iter()
+            .enumerate()
+            .map(|(i, setup)| {
+                let mut route = layout.route(i);
+                route.rotate_left(setup.start_step);
+                Ok(VirtualTrain {
+                    train: setup.train(i as u64)?,
+                    phase: Phase::Requesting {
+                        order: Vec::new(),
+                        next: 0,
+                    },
+                    route,
+                    step: 0,
+                    last_track: None,
+                    version: 0,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let mut locks = LockState::new(layout.segments.len());
+        for (train, setup) in setups.iter().enumerate() {
+            locks.priorities.insert(train as u64, setup.priority);
+        }
+
+        let mut simulation = VirtualSimulation {
+            tracks: layout.tracks()?,
+            events: layout.event_log(),
+            strategy: sync.build(layout),
+            policy: GrantPolicy::default(),
+            locks,
+            trains,
+            clock: Duration::ZERO,
+            queue: BinaryHeap::new(),
+            sequence: 0,
+            rng: SplitMix64(seed),
+        };
+        // trens desligados nunca pedem trilhos
+        for (train, setup) in setups.iter().enumerate() {
+            if setup.enabled {
+                simulation.request_step(train);
+            }
+        }
+        Ok(simulation)
+    }
+
+    /// Ordem de entrega dos trilhos disputados; a ordem arbitrária sorteia.
+    pub fn set_grant_policy(&mut self, policy: GrantPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn clock(&self) -> Duration {
+        self.clock
+    }
+
+    pub fn train(&self, train: usize) -> &Train {
+        &self.trains[train].train
+    }
+
+    /// Trilho atual do trem, ou o último que ele ocupou se está esperando.
+    pub fn current_track(&self, train: usize) -> usize {
+        let train = &self.trains[train];
+        train.last_track.unwrap_or(train.route[0][0])
+    }
+
+    /// Muda a velocidade no instante atual, inclusive no meio do trilho.
+    pub fn set_velocity(&mut self, train: usize, velocity: f64) -> Result<(), Error> {
+        let clock = self.clock;
+        let virtual_train = self
+            .trains
+            .get_mut(train)
+            .ok_or(Error::UnknownTrain(train))?;
+        let before = virtual_train.train.velocity;
+        virtual_train.train.set_velocity(velocity)?;
+        if let Phase::Traversing {
+            remaining, since, ..
+        } = &mut virtual_train.phase
+        {
+            let elapsed = (clock - *since).as_secs_f64();
+            *remaining = (*remaining - before * elapsed).max(0.0);
+            *since = clock;
+        }
+        virtual_train.version += 1;
+
+        let (id, velocity) = (virtual_train.train.id, virtual_train.train.velocity);
+        self.events
+            .record_at(clock, id, EventKind::VelocityChanged(velocity));
+        self.schedule(train);
+        Ok(())
+    }
+
+    /// Processa todos os eventos até `end`, chamando `on_enter` com o
+    /// instante, o trem e o trilho sempre que um trem entra em um trilho.
+    pub fn run_until<F>(&mut self, end: Duration, mut on_enter: F)
+    where
+        F: FnMut(Duration, usize, &Track),
+    {
+        self.grant(&mut on_enter);
+
+        while let Some(Reverse((time, _, train, version))) = self.queue.peek().cloned() {
+            if time > end {
+                break;
+            }
+            self.queue.pop();
+            if version != self.trains[train].version {
+                continue;
+            }
+
+            self.clock = time;
+            self.finish_track(train, &mut on_enter);
+            self.grant(&mut on_enter);
+        }
+
+        self.clock = self.clock.max(end);
+    }
+
+    fn request_step(&mut self, train: usize) {
+        let virtual_train = &mut self.trains[train];
+        let step = virtual_train.route[virtual_train.step].clone();
+
+        let id = virtual_train.train.id;
+        let order = self.strategy.order(&step);
+
+        self.locks.claims.insert(id, step);
+        self.locks.enqueue(id, order[0]);
+        self.events
+            .record_at(self.clock, id, EventKind::Requested(order[0]));
+        virtual_train.phase = Phase::Requesting { order, next: 0 };
+    }
+
+    /// Concede trilhos livres aos trens que esperam, sorteando entre os
+    /// que podem ser atendidos ao mesmo tempo e que a política deixa.
+    fn grant<F>(&mut self, on_enter: &mut F)
+    where
+        F: FnMut(Duration, usize, &Track),
+    {
+        loop {
+            let candidates: Vec<usize> = (0..self.trains.len())
+                .filter(|train| match self.wanted(*train) {
+                    Some(segment) => {
+                        let id = self.trains[*train].train.id;
+                        self.locks
+                            .grantable(&*self.strategy, self.policy, id, segment)
+                    }
+                    None => false,
+                })
+                .collect();
+            if candidates.is_empty() {
+                return;
+            }
+
+            let train = candidates[self.rng.below(candidates.len())];
+            let segment = self.wanted(train).unwrap();
+            let id = self.trains[train].train.id;
+
+            self.locks.grant(id, segment);
+            self.events
+                .record_at(self.clock, id, EventKind::Acquired(segment));
+
+            if let Phase::Requesting { order, next } = &mut self.trains[train].phase {
+                *next += 1;
+                match order.get(*next) {
+                    Some(segment) => {
+                        self.locks.enqueue(id, *segment);
+                        let kind = EventKind::Requested(*segment);
+                        self.events.record_at(self.clock, id, kind);
+                    }
+                    None => self.enter_track(train, 0, on_enter),
+                }
+            }
+        }
+    }
+
+    fn wanted(&self, train: usize) -> Option<usize> {
+        match &self.trains[train].phase {
+            Phase::Requesting { order, next } => order.get(*next).cloned(),
+            Phase::Traversing { .. } => None,
+        }
+    }
+
+    fn enter_track<F>(&mut self, train: usize, index: usize, on_enter: &mut F)
+    where
+        F: FnMut(Duration, usize, &Track),
+    {
+        let virtual_train = &mut self.trains[train];
+        let segment = virtual_train.route[virtual_train.step][index];
+        virtual_train.phase = Phase::Traversing {
+            index,
+            remaining: self.tracks[segment].distance,
+            since: self.clock,
+        };
+        virtual_train.last_track = Some(segment);
+        virtual_train.version += 1;
+
+        let id = virtual_train.train.id;
+        self.events
+            .record_at(self.clock, id, EventKind::Entered(segment));
+
+        on_enter(self.clock, train, &self.tracks[segment]);
+        self.schedule(train);
+    }
+
+    fn finish_track<F>(&mut self, train: usize, on_enter: &mut F)
+    where
+        F: FnMut(Duration, usize, &Track),
+    {
+        let virtual_train = &self.trains[train];
+        let id = virtual_train.train.id;
+        let index = match virtual_train.phase {
+            Phase::Traversing { index, .. } => index,
+            Phase::Requesting { .. } => return,
+        };
+        let step = &virtual_train.route[virtual_train.step];
+        let segment = step[index];
+        let last = index + 1 == step.len();
+
+        self.locks.release(id, segment);
+        self.events
+            .record_at(self.clock, id, EventKind::Released(segment));
+
+        if last {
+            let virtual_train = &mut self.trains[train];
+            virtual_train.step = (virtual_train.step + 1) % virtual_train.route.len();
+            self.request_step(train);
+        } else {
+            self.enter_track(train, index + 1, on_enter);
+        }
+    }
+
+    fn schedule(&mut self, train: usize) {
+        let virtual_train = &self.trains[train];
+        let velocity = virtual_train.train.velocity;
+        if let Phase::Traversing {
+            remaining, since, ..
+        } = virtual_train.phase
+        {
+            if velocity <= 0.0 {
+                return;
+            }
+
+            let time = since + Duration::from_secs_f64(remaining / velocity);
+            self.queue
+                .push(Reverse((time, self.sequence, train, virtual_train.version)));
+            self.sequence += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Event, PoolSimulation};
+
+    fn run(seed: u64, stops: &[u64]) -> (Vec<(Duration, usize, String)>, Vec<Event>) {
+        let layout = Layout::from_ron(include_str!("../assets/layouts/four_trains.ron")).unwrap();
+        let mut simulation =
+            VirtualSimulation::new(&layout, SyncKind::Banker, &[1.0, 2.0, 3.0, 4.0], seed).unwrap();
+        let mut entered = Vec::new();
+        for stop in stops {
+            simulation.run_until(Duration::from_secs(*stop), |time, train, track| {
+                entered.push((time, train, track.name.clone()))
+            });
+        }
+        (entered, simulation.events.events())
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_trace() {
+        let (entered, events) = run(7, &[120]);
+        assert!(entered.len() > 10);
+        assert_eq!(run(7, &[120]), (entered, events));
+    }
+
+    #[test]
+    fn stopping_midway_does_not_change_the_trace() {
+        assert_eq!(run(7, &[30, 75, 120]), run(7, &[120]));
+    }
+
+    #[test]
+    fn rejects_a_setup_per_train_mismatch() {
+        let layout = Layout::chain(3).unwrap();
+        let setups = TrainSetup::defaults(&layout, &[]);
+        let result = VirtualSimulation::with_setups(&layout, SyncKind::Mutex, &setups[..2], 0);
+        assert!(matches!(
+            result,
+            Err(Error::WrongTrainCount {
+                expected: 3,
+                found: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn pool_rejects_bad_setups_before_building_machines() {
+        let layout = Layout::chain(3).unwrap();
+        let mut setups = TrainSetup::defaults(&layout, &[]);
+        let mut extra = setups.clone();
+        extra.push(setups[0].clone());
+        assert!(matches!(
+            PoolSimulation::new(layout.clone(), SyncKind::Mutex, extra, 0),
+            Err(Error::WrongTrainCount {
+                expected: 3,
+                found: 4
+            })
+        ));
+
+        setups[1].start_step = 9;
+        assert!(matches!(
+            PoolSimulation::new(layout, SyncKind::Mutex, setups, 0),
+            Err(Error::UnknownStep { step: 9, .. })
+        ));
+    }
+}